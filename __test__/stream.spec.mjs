@@ -0,0 +1,69 @@
+import { setTimeout as sleep } from 'timers/promises'
+
+import test from 'ava'
+
+import espeak from '../main.js'
+
+const { clearPhonemeCache, getPhonemeCacheStats, phonemizeSentences, phonemizeStream, setPhonemeCacheCapacity } =
+  espeak
+
+const text = 'The cat sat. It slept. Then it woke up.'
+
+test.serial('phonemizeSentences yields each sentence with its offsets', async (t) => {
+  const sentences = []
+  for await (const sentence of phonemizeSentences(text, false)) {
+    sentences.push(sentence)
+  }
+
+  t.deepEqual(
+    sentences.map(({ text, start, end }) => [text, start, end]),
+    [
+      ['The cat sat.', 0, 12],
+      ['It slept.', 13, 22],
+      ['Then it woke up.', 23, 39],
+    ],
+  )
+  t.true(sentences.every(({ phonemes }) => phonemes.length > 0))
+})
+
+test.serial('phonemizeSentences only phonemizes the sentences asked for', async (t) => {
+  // Each sentence phonemized misses the cache once
+  setPhonemeCacheCapacity(16)
+  clearPhonemeCache()
+  t.teardown(() => setPhonemeCacheCapacity(0))
+
+  const sentences = phonemizeSentences(text, false)
+  await sentences.next()
+  await sleep(100)
+  t.is(getPhonemeCacheStats().misses, 1)
+
+  await sentences.return()
+  await sleep(100)
+  t.is(getPhonemeCacheStats().misses, 1)
+})
+
+test.serial('phonemizeStream waits for the callback before the next sentence', async (t) => {
+  setPhonemeCacheCapacity(16)
+  clearPhonemeCache()
+  t.teardown(() => setPhonemeCacheCapacity(0))
+
+  const misses = []
+  await phonemizeStream(text, false, async (sentence) => {
+    misses.push(getPhonemeCacheStats().misses)
+    await sleep(50)
+    return !sentence.text.startsWith('It')
+  })
+
+  // The stream stopped after the second sentence without phonemizing the third
+  t.deepEqual(misses, [1, 2])
+  t.is(getPhonemeCacheStats().misses, 2)
+})
+
+test('phonemizeStream rejects when the callback throws', async (t) => {
+  await t.throwsAsync(
+    phonemizeStream(text, false, () => {
+      throw new Error('consumer failed')
+    }),
+    { message: 'consumer failed' },
+  )
+})
@@ -1,19 +1,110 @@
+use std::thread;
+
 use criterion::BenchmarkId;
 use criterion::Criterion;
 use criterion::{criterion_group, criterion_main};
+use espeak_ng_rs::phonetics::internal::{phonemize_batch, string_to_phonetics};
+use espeak_ng_rs::voice::DEFAULT_VOICE;
+use espeak_ng_rs::{text_to_phonemes, PhonemizeOptions};
+
+const THREADS: usize = 4;
+const BATCH_SIZE: usize = 100;
+
+// Mirrors the previous behaviour of taking the global lock and loading the voice on every call
+fn text_to_phonemes_reinitialising(text: &str) -> String {
+  let mut speaker = espeakng::initialise(None).unwrap().lock();
+  speaker.set_voice_raw("gmw/en-US").unwrap();
+
+  text
+    .split_inclusive([',', '.', '?', '!'])
+    .map(|text| {
+      speaker
+        .text_to_phonemes(
+          text,
+          espeakng::PhonemeGenOptions::Standard {
+            text_mode: espeakng::TextMode::Utf8,
+            phoneme_mode: espeakng::PhonemeMode::IncludeZeroWidthJoiners,
+          },
+        )
+        .unwrap()
+        .unwrap()
+    })
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+fn run_concurrently(text: &str, phonemize: fn(&str) -> String) {
+  thread::scope(|scope| {
+    for _ in 0..THREADS {
+      scope.spawn(|| phonemize(text));
+    }
+  });
+}
 
-// This is a struct that tells Criterion.rs to use the "futures" crate's current-thread executor
 fn criterion_benchmark(c: &mut Criterion) {
-  let input_text = "Large piece of text";
-
-  // c.bench_with_input(
-  //   BenchmarkId::new("espeak_threaded", input_text),
-  //   &input_text,
-  //   |b, &s| {
-  //     b.to_async(Runtime::new().unwrap())
-  //       .iter(|| EspeakRunner::run_phoneme_task(s.to_owned()));
-  //   },
-  // );
+  let input_text = "The quick brown fox jumps over the lazy dog. It was, by all accounts, a rather large piece of text!";
+
+  let mut group = c.benchmark_group("text_to_phonemes");
+  group.bench_with_input(
+    BenchmarkId::new("reinitialising", "sequential"),
+    &input_text,
+    |b, &s| b.iter(|| text_to_phonemes_reinitialising(s)),
+  );
+  group.bench_with_input(
+    BenchmarkId::new("speaker_queue", "sequential"),
+    &input_text,
+    |b, &s| b.iter(|| text_to_phonemes(s, DEFAULT_VOICE, &PhonemizeOptions::default()).unwrap()),
+  );
+  group.bench_with_input(
+    BenchmarkId::new("reinitialising", "threaded"),
+    &input_text,
+    |b, &s| b.iter(|| run_concurrently(s, text_to_phonemes_reinitialising)),
+  );
+  group.bench_with_input(
+    BenchmarkId::new("speaker_queue", "threaded"),
+    &input_text,
+    |b, &s| {
+      b.iter(|| {
+        run_concurrently(s, |text| {
+          text_to_phonemes(text, DEFAULT_VOICE, &PhonemizeOptions::default()).unwrap()
+        })
+      })
+    },
+  );
+  group.finish();
+
+  let texts = vec![input_text.to_string(); BATCH_SIZE];
+  let mut group = c.benchmark_group("phonemize_batch");
+  group.bench_with_input(
+    BenchmarkId::new("individually", BATCH_SIZE),
+    &texts,
+    |b, texts| {
+      b.iter(|| {
+        texts
+          .iter()
+          .map(|text| {
+            string_to_phonetics(text, true, DEFAULT_VOICE, &PhonemizeOptions::default()).unwrap()
+          })
+          .collect::<Vec<_>>()
+      })
+    },
+  );
+  group.bench_with_input(
+    BenchmarkId::new("batched", BATCH_SIZE),
+    &texts,
+    |b, texts| {
+      b.iter(|| {
+        phonemize_batch(
+          texts.clone(),
+          true,
+          DEFAULT_VOICE,
+          &PhonemizeOptions::default(),
+        )
+        .unwrap()
+      })
+    },
+  );
+  group.finish();
 }
 
 criterion_group!(benches, criterion_benchmark);
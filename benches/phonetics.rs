@@ -1,15 +1,23 @@
 use criterion::BenchmarkId;
 use criterion::Criterion;
 use criterion::{criterion_group, criterion_main};
+use espeak_ng_rs::boundary::{BoundaryProfile, PRESERVED_PUNCTUATION};
 use espeak_ng_rs::phonetics::punctuation::extract_punctuation;
 // This is a struct that tells Criterion.rs to use the "futures" crate's current-thread executor
 fn criterion_benchmark(c: &mut Criterion) {
   let input_text = "This, is a piece of text, that has punctuations.";
-
+  // The punctuation phonemize preserves when no boundaries are passed
   c.bench_with_input(
     BenchmarkId::new("espeak_threaded", input_text),
     &input_text,
-    |b, &s| b.iter(|| extract_punctuation(s)),
+    |b, &s| b.iter(|| extract_punctuation(s, &PRESERVED_PUNCTUATION)),
+  );
+
+  let boundaries = BoundaryProfile::for_language("en");
+  c.bench_with_input(
+    BenchmarkId::new("espeak_threaded_language_profile", input_text),
+    &input_text,
+    |b, &s| b.iter(|| extract_punctuation(s, &boundaries)),
   );
 }
 
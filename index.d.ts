@@ -3,9 +3,90 @@
 
 /* auto-generated by NAPI-RS */
 
-export function forceAlignPhonemesGraphemes(text: string, phonemes: string, alignPhonemes: boolean): Promise<NestedChunk>
-export function forceAlignPhonemesGraphemesList(text: string, endTimes: Array<number>, phonemesList: Array<string>, alignPhonemes: boolean): Promise<NestedChunk>
-export function phonemize(text: string, preservePunctuation: boolean): Promise<string>
+export interface AlignOptions {
+  /** Treats the text as SSML, so markup isn't split into words */
+  ssml?: boolean
+  /**
+   * Aligns against the text with numbers, dates, times, currencies, ordinals and units expanded
+   * into words. Chunks still point into the original text. The words are English, so other
+   * voices fail with INVALID_OPTION. Ignored for SSML
+   */
+  normalize?: boolean
+  /** Includes the phonemes matched to each word, along with a chunk per phoneme */
+  includePhonemes?: boolean
+  /** How words are matched to phoneme words, the legacy heuristic by default */
+  algorithm?: AlignAlgorithm
+  /**
+   * Also groups the words into paragraphs and sentences, each with their own offsets and times.
+   * Ignored when aligning phonemes
+   */
+  groupSentences?: boolean
+  /**
+   * The unit chunk offsets are counted in, chars by default. JavaScript strings index by UTF-16
+   * code units
+   */
+  offsetUnit?: OffsetUnit
+  /** How the text is split into words, on Unicode word boundaries by default */
+  segmentation?: WordSegmentation
+  /**
+   * Which punctuation separates words in the text and the phoneme list, the common preset by
+   * default
+   */
+  boundaries?: BoundaryProfile
+  /**
+   * The voice complex tokens and compounds are looked up in to find the words they're spoken
+   * as, and whose lexicon overrides are recognised in the phonemes, en-us by default. Ignored
+   * by `forceAlignSynthesized`, which is given its voice
+   */
+  voice?: string
+}
+export const enum MatchKind {
+  /** The word's letters match its phonemes exactly */
+  Exact = 0,
+  /** The word's letters roughly match its phonemes */
+  Fuzzy = 1,
+  /** A complex token such as a number, matched to the words espeak speaks it as */
+  Complex = 2,
+  /** The word was found again after the text and phonemes drifted apart */
+  DesyncRecovered = 3,
+  /** The word's times were split from its neighbours' or guessed, as it had no phonemes of its own */
+  Interpolated = 4,
+  /** A hyphenated compound or contraction, matched to the words espeak speaks its parts as */
+  Compound = 5
+}
+export const enum AlignAlgorithm {
+  /** Walks words and phoneme words in step, patching desyncs as it finds them */
+  Heuristic = 0,
+  /** Finds the best alignment of the whole text at once with dynamic programming */
+  Global = 1
+}
+export function forceAlignPhonemesGraphemes(text: string, phonemes: string, alignPhonemes: boolean, options?: AlignOptions | undefined | null): Promise<NestedChunk>
+export function forceAlignPhonemesGraphemesList(text: string, endTimes: Array<number>, phonemesList: Array<string>, alignPhonemes: boolean, options?: AlignOptions | undefined | null): Promise<NestedChunk>
+/**
+ * Aligns the text against espeak's own synthesis of it, so the word times are real milliseconds
+ * rather than coming from an external model
+ */
+export function forceAlignSynthesized(text: string, voice?: string | undefined | null, options?: AlignOptions | undefined | null): Promise<NestedChunk>
+export function phonemize(text: string, preservePunctuation: boolean, voice?: string | undefined | null, options?: PhonemizeOptions | undefined | null): Promise<string>
+export interface PhonemizeOptions {
+  /** Parses the text as SSML rather than speaking markup literally */
+  ssml?: boolean
+  /** The notation phonemes are written in, IPA by default */
+  alphabet?: PhonemeAlphabet
+  /** How multi-letter phonemes are joined, not joined by default */
+  tie?: PhonemeTie
+  /**
+   * Expands numbers, dates, times, currencies, ordinals and units into words before espeak
+   * reads them. The words are English, so other voices fail with INVALID_OPTION. Ignored for
+   * SSML
+   */
+  normalize?: boolean
+  /**
+   * Which punctuation is kept when preserving punctuation. Defaults to the original
+   * `!(),-.:;?`, so language presets from `boundaryProfile` have to be passed in
+   */
+  boundaries?: BoundaryProfile
+}
 export interface NestedChunk {
   value: string
   start: number
@@ -13,6 +94,13 @@ export interface NestedChunk {
   startTime: number
   endTime: number
   chunks: Array<Chunk>
+  /** Mean confidence of the aligned words, from 0 to 1 */
+  quality?: number
+  /**
+   * The words grouped into paragraphs and sentences, when `groupSentences` is set and text
+   * rather than phonemes is aligned
+   */
+  paragraphs?: Array<ParagraphChunk>
 }
 export interface Chunk {
   value: string
@@ -20,6 +108,22 @@ export interface Chunk {
   end: number
   startTime: number
   endTime: number
+  /** The phonemes matched to the word, when alignment includes phonemes */
+  phonemes?: string
+  /** Range of the word's phonemes in the phoneme list */
+  phonemeStart?: number
+  phonemeEnd?: number
+  /** One chunk per phoneme, whose offsets index the phoneme list */
+  phonemeChunks?: Array<Chunk>
+  /** How confident the aligner is in the word's match, from 0 to 1 */
+  confidence?: number
+  /** How the word was matched to its phonemes */
+  matchKind?: MatchKind
+  /**
+   * The parts of a hyphenated compound or contraction, each aligned to the phoneme words
+   * espeak spoke it as
+   */
+  parts?: Array<Chunk>
 }
 export interface PhonemeChunk {
   value: string
@@ -29,3 +133,237 @@ export interface PhonemeChunk {
   startTime: number
   endTime: number
 }
+export interface VoiceInfo {
+  name: string
+  identifier: string
+  languages: Array<string>
+}
+export function listVoices(): Promise<Array<VoiceInfo>>
+/** Sets how many phonemization or alignment jobs may run at once. Jobs already running keep their permit */
+export function setMaxConcurrency(maxConcurrency: number): void
+export interface SynthesisOptions {
+  voice?: string
+  /** Words per minute, between 80 and 450 */
+  rate?: number
+  /** Base pitch, between 0 and 100 */
+  pitch?: number
+  /** Volume, between 0 and 200. Values above 100 may distort */
+  volume?: number
+  /** Wraps the samples in a WAV header rather than returning raw PCM */
+  wav?: boolean
+}
+export interface SynthesisResult {
+  /** 16-bit little endian mono PCM, or a WAV file if requested */
+  audio: Buffer
+  sampleRate: number
+}
+export function synthesize(text: string, options?: SynthesisOptions | undefined | null): Promise<SynthesisResult>
+export const enum PhonemeAlphabet {
+  /** International Phonetic Alphabet */
+  Ipa = 0,
+  /** espeak's ASCII phoneme mnemonics, as printed by `espeak-ng -x` */
+  Ascii = 1,
+  /** Kirshenbaum ASCII-IPA, converted from espeak's IPA output */
+  Kirshenbaum = 2
+}
+export const enum PhonemeTie {
+  /** Multi-letter phonemes such as tʃ are written as separate letters */
+  None = 0,
+  /** Joins multi-letter phonemes with a tie bar (U+0361), e.g. t͡ʃ */
+  TieBar = 1,
+  /** Joins multi-letter phonemes with a zero width joiner (U+200D) */
+  ZeroWidthJoiner = 2
+}
+export const enum Stress {
+  None = 0,
+  Primary = 1,
+  Secondary = 2
+}
+export interface Syllable {
+  /** The syllable's phonemes, without stress marks */
+  phonemes: string
+  stress: Stress
+}
+export interface PhonemizedWord {
+  /**
+   * The word's phonemes as espeak wrote them, including stress marks. Words espeak runs
+   * together, such as "ɪnðə", are split apart
+   */
+  phonemes: string
+  syllables: Array<Syllable>
+}
+export function phonemizeSyllables(text: string, voice?: string | undefined | null, options?: PhonemizeOptions | undefined | null): Promise<Array<PhonemizedWord>>
+export interface WordPhonemes {
+  /** The source text espeak read for the word */
+  text: string
+  /** Offset of the word in the source text, in chars */
+  start: number
+  end: number
+  /** The phonemes espeak spoke for the word, without stress marks */
+  phonemes: string
+}
+/**
+ * Phonemizes the text into a list of words, each with the span of source text espeak read for
+ * it, rather than leaving callers to split the phonemes on spaces
+ */
+export function phonemizeWords(text: string, voice?: string | undefined | null, options?: PhonemizeOptions | undefined | null): Promise<Array<WordPhonemes>>
+export interface LexiconEntry {
+  /** The word to override, matched case-insensitively, or a pattern if `regex` is set */
+  word: string
+  /** The pronunciation to use instead of espeak's, in IPA */
+  phonemes: string
+  /** Only overrides voices of this language, such as "en" or "en-gb" */
+  language?: string
+  /** Treats `word` as a regular expression */
+  regex?: boolean
+}
+/** Adds pronunciation overrides, which take precedence over espeak for matching words */
+export function addLexiconEntries(entries: Array<LexiconEntry>): void
+/**
+ * Adds the overrides from a lexicon file. `.json` files hold a list of entries or an object of
+ * words to phonemes, anything else is read as tab separated `word, phonemes, language` lines.
+ * Returns the number of entries added
+ */
+export function loadLexicon(path: string): number
+export function clearLexicon(): void
+export interface DictionarySource {
+  /** Contents of the language's `_rules` file */
+  rules: string
+  /** Contents of the language's `_list` file */
+  list: string
+  /** Contents of the optional `_listx` file, read after `_list` */
+  listx?: string
+  /** Contents of the optional `_extra` file */
+  extra?: string
+  /** Contents of the optional `_emoji` file */
+  emoji?: string
+}
+export function setDataPath(path?: string | undefined | null): Promise<void>
+/**
+ * Compiles `_rules` and `_list` sources for the language into a temporary copy of espeak's data,
+ * so phonemization uses them without reinstalling espeak-ng. Returns the directory espeak now
+ * loads from
+ */
+export function compileDictionary(language: string, source: DictionarySource): Promise<string>
+export interface PhonemizeResult {
+  phonemes?: string
+  /** Why the text couldn't be phonemized, in the same "CODE: message" form as thrown errors */
+  error?: string
+}
+/**
+ * Phonemizes many texts in one call, returning a result for each in the same order. A text
+ * which fails to phonemize sets its `error` rather than rejecting the whole batch
+ */
+export function phonemizeBatch(texts: Array<string>, preservePunctuation: boolean, voice?: string | undefined | null, options?: PhonemizeOptions | undefined | null): Promise<Array<PhonemizeResult>>
+export interface PhonemizedSentence {
+  text: string
+  /** Offset of the sentence in the source text, in chars */
+  start: number
+  end: number
+  phonemes: string
+}
+/**
+ * Phonemizes a text a sentence at a time, only phonemizing each sentence once it's asked for so
+ * a slow consumer is never flooded. Backs `phonemizeSentences` and `phonemizeStream`
+ */
+export class PhonemeSentences {
+  constructor(text: string, preservePunctuation: boolean, voice?: string | undefined | null, options?: PhonemizeOptions | undefined | null)
+  /** Phonemizes the next sentence, resolving to null once every sentence has been returned */
+  next(): Promise<PhonemizedSentence | null>
+}
+export interface PhonemeCacheStats {
+  hits: number
+  misses: number
+  /** Number of cached results */
+  size: number
+  /** Maximum number of cached results, 0 when caching is disabled */
+  capacity: number
+}
+/**
+ * Caches up to `capacity` phonemization results keyed on their text, voice and options.
+ * Caching is off by default, and setting the capacity to 0 turns it off again
+ */
+export function setPhonemeCacheCapacity(capacity: number): void
+export function getPhonemeCacheStats(): PhonemeCacheStats
+export function clearPhonemeCache(): void
+export interface SegmentOptions {
+  /** Abbreviations to recognise on top of the built in English ones, with or without their full stop */
+  abbreviations?: Array<string>
+}
+export interface Sentence {
+  text: string
+  /** Offset of the sentence in the source text, in chars */
+  start: number
+  end: number
+}
+/** Splits the text into sentences with their char offsets, the same way phonemization does */
+export function segmentSentences(text: string, options?: SegmentOptions | undefined | null): Array<Sentence>
+export interface NormalizedSpan {
+  /** Offset of the expansion in the normalized text, in chars */
+  start: number
+  end: number
+  /** Offset of the token it replaced in the original text, in chars */
+  originalStart: number
+  originalEnd: number
+}
+export interface NormalizedText {
+  text: string
+  /** Expanded tokens in order. Text between them is unchanged, so maps back one to one */
+  spans: Array<NormalizedSpan>
+}
+/**
+ * Expands numbers, dates, times, currencies, ordinals and units into words, returning the
+ * expanded text along with where each expansion came from in the original
+ */
+export function normalizeText(text: string): NormalizedText
+export const enum OffsetUnit {
+  /** Unicode scalar values, as Rust's `chars` counts them */
+  Chars = 0,
+  /** UTF-16 code units, as JavaScript strings are indexed */
+  Utf16 = 1,
+  /** UTF-8 bytes */
+  Utf8Bytes = 2,
+  /** Extended grapheme clusters, so an emoji or accented letter counts once */
+  Graphemes = 3
+}
+export const enum WordSegmentation {
+  /** Unicode word boundaries (UAX #29), which never split a grapheme cluster */
+  Unicode = 0,
+  /** The original chunker, which splits on a fixed set of punctuation and whitespace */
+  Legacy = 1
+}
+/**
+ * Which characters separate words and count as punctuation, shared by text chunking, phoneme
+ * word splitting and punctuation preservation so they all agree
+ */
+export interface BoundaryProfile {
+  /**
+   * Characters treated as punctuation. They separate words in the text and the phoneme list,
+   * and are kept when phonemizing with punctuation preserved
+   */
+  punctuation: string
+  /**
+   * Punctuation which doesn't separate words when it falls between two letters or digits, such
+   * as the hyphen in "well-known"
+   */
+  joiners: string
+}
+/** The boundary preset for a language or voice, to adjust and pass back in the options */
+export function boundaryProfile(language: string): BoundaryProfile
+export interface SentenceChunk {
+  value: string
+  start: number
+  end: number
+  startTime: number
+  endTime: number
+  /** The sentence's words */
+  chunks: Array<Chunk>
+}
+export interface ParagraphChunk {
+  value: string
+  start: number
+  end: number
+  startTime: number
+  endTime: number
+  sentences: Array<SentenceChunk>
+}
@@ -252,8 +252,34 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { forceAlignPhonemesGraphemes, forceAlignPhonemesGraphemesList, phonemize } = nativeBinding
+const { forceAlignPhonemesGraphemes, forceAlignPhonemesGraphemesList, phonemize, listVoices, setMaxConcurrency, forceAlignSynthesized, synthesize, PhonemeAlphabet, PhonemeTie, Stress, phonemizeSyllables, phonemizeWords, addLexiconEntries, loadLexicon, clearLexicon, setDataPath, compileDictionary, phonemizeBatch, PhonemeSentences, setPhonemeCacheCapacity, getPhonemeCacheStats, clearPhonemeCache, segmentSentences, normalizeText, AlignAlgorithm, MatchKind, OffsetUnit, WordSegmentation, boundaryProfile } = nativeBinding
 
 module.exports.forceAlignPhonemesGraphemes = forceAlignPhonemesGraphemes
 module.exports.forceAlignPhonemesGraphemesList = forceAlignPhonemesGraphemesList
 module.exports.phonemize = phonemize
+module.exports.listVoices = listVoices
+module.exports.setMaxConcurrency = setMaxConcurrency
+module.exports.forceAlignSynthesized = forceAlignSynthesized
+module.exports.synthesize = synthesize
+module.exports.PhonemeAlphabet = PhonemeAlphabet
+module.exports.PhonemeTie = PhonemeTie
+module.exports.Stress = Stress
+module.exports.phonemizeSyllables = phonemizeSyllables
+module.exports.phonemizeWords = phonemizeWords
+module.exports.addLexiconEntries = addLexiconEntries
+module.exports.loadLexicon = loadLexicon
+module.exports.clearLexicon = clearLexicon
+module.exports.setDataPath = setDataPath
+module.exports.compileDictionary = compileDictionary
+module.exports.phonemizeBatch = phonemizeBatch
+module.exports.PhonemeSentences = PhonemeSentences
+module.exports.setPhonemeCacheCapacity = setPhonemeCacheCapacity
+module.exports.getPhonemeCacheStats = getPhonemeCacheStats
+module.exports.clearPhonemeCache = clearPhonemeCache
+module.exports.segmentSentences = segmentSentences
+module.exports.normalizeText = normalizeText
+module.exports.AlignAlgorithm = AlignAlgorithm
+module.exports.MatchKind = MatchKind
+module.exports.OffsetUnit = OffsetUnit
+module.exports.WordSegmentation = WordSegmentation
+module.exports.boundaryProfile = boundaryProfile
@@ -0,0 +1,17 @@
+/* Hand-written types for main.js, alongside the types napi generates in index.d.ts */
+
+import type { PhonemizedSentence, PhonemizeOptions } from './index'
+
+export * from './index'
+
+/**
+ * Phonemizes the text a sentence at a time as an async iterator. Each sentence is only
+ * phonemized once the loop asks for it, and breaking out of the loop stops phonemizing the rest
+ */
+export function phonemizeSentences(text: string, preservePunctuation: boolean, voice?: string | undefined | null, options?: PhonemizeOptions | undefined | null): AsyncGenerator<PhonemizedSentence, void>
+/**
+ * Phonemizes the text a sentence at a time, calling back with each sentence. The next sentence
+ * isn't phonemized until the callback returns, or the promise it returns resolves. Returning
+ * `false` stops the stream, and an error thrown by the callback rejects the returned promise
+ */
+export function phonemizeStream(text: string, preservePunctuation: boolean, callback: (sentence: PhonemizedSentence) => boolean | void | Promise<boolean | void>, voice?: string | undefined | null, options?: PhonemizeOptions | undefined | null): Promise<void>
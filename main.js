@@ -0,0 +1,33 @@
+/* Hand-written entry point. index.js and index.d.ts are generated by `napi build`, so anything
+ * built on top of the native binding in JavaScript lives here instead */
+
+const binding = require('./index.js')
+
+const { PhonemeSentences } = binding
+
+/**
+ * Phonemizes the text a sentence at a time as an async iterator. Each sentence is only
+ * phonemized once the loop asks for it, and breaking out of the loop stops phonemizing the rest
+ */
+async function* phonemizeSentences(text, preservePunctuation, voice, options) {
+  const sentences = new PhonemeSentences(text, preservePunctuation, voice, options)
+  let sentence
+  while ((sentence = await sentences.next()) != null) {
+    yield sentence
+  }
+}
+
+/**
+ * Phonemizes the text a sentence at a time, calling back with each sentence. The next sentence
+ * isn't phonemized until the callback returns, or the promise it returns resolves. Returning
+ * `false` stops the stream, and an error thrown by the callback rejects the returned promise
+ */
+async function phonemizeStream(text, preservePunctuation, callback, voice, options) {
+  for await (const sentence of phonemizeSentences(text, preservePunctuation, voice, options)) {
+    if ((await callback(sentence)) === false) {
+      break
+    }
+  }
+}
+
+module.exports = { ...binding, phonemizeSentences, phonemizeStream }
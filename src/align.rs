@@ -1,42 +1,156 @@
+use espeakng::bindings;
+use levenshtein::levenshtein;
+use napi::bindgen_prelude::*;
 use napi_derive::napi;
 use once_cell::sync::Lazy;
 use regex::Regex;
 
 use crate::{
-  leven::get_average_leven, text_to_phonemes, transform_raw_phoneme_timestamps, Chunk, NestedChunk,
-  PhonemeChunk,
+  blocking,
+  boundary::{BoundaryProfile, DEFAULT_BOUNDARIES},
+  document::{group_chunks, ParagraphChunk},
+  error::{Error, Result},
+  is_phonetic_word_boundary,
+  leven::{get_average_leven, leven_phoneme_relative},
+  lexicon,
+  normalize::{normalize_for_voice, NormalizedText},
+  offsets::{convert_offsets, OffsetUnit},
+  phonetics::words::phonemize_words,
+  sequence::align_sequences,
+  speaker,
+  synth::{synthesize_with_events, SynthesisEventKind},
+  tokenize::{split_compound, split_words, WordSegmentation},
+  transform_raw_phoneme_timestamps,
+  voice::DEFAULT_VOICE,
+  Chunk, NestedChunk, PhonemeChunk, PhonemizeOptions, PHONETIC_WORD_TAG_BOUNDARIES,
 };
 
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct AlignOptions {
+  /// Treats the text as SSML, so markup isn't split into words
+  pub ssml: Option<bool>,
+  /// Aligns against the text with numbers, dates, times, currencies, ordinals and units expanded
+  /// into words. Chunks still point into the original text. The words are English, so other
+  /// voices fail with INVALID_OPTION. Ignored for SSML
+  pub normalize: Option<bool>,
+  /// Includes the phonemes matched to each word, along with a chunk per phoneme
+  pub include_phonemes: Option<bool>,
+  /// How words are matched to phoneme words, the legacy heuristic by default
+  pub algorithm: Option<AlignAlgorithm>,
+  /// Also groups the words into paragraphs and sentences, each with their own offsets and times.
+  /// Ignored when aligning phonemes
+  pub group_sentences: Option<bool>,
+  /// The unit chunk offsets are counted in, chars by default. JavaScript strings index by UTF-16
+  /// code units
+  pub offset_unit: Option<OffsetUnit>,
+  /// How the text is split into words, on Unicode word boundaries by default
+  pub segmentation: Option<WordSegmentation>,
+  /// Which punctuation separates words in the text and the phoneme list, the common preset by
+  /// default
+  pub boundaries: Option<BoundaryProfile>,
+  /// The voice complex tokens and compounds are looked up in to find the words they're spoken
+  /// as, and whose lexicon overrides are recognised in the phonemes, en-us by default. Ignored
+  /// by `forceAlignSynthesized`, which is given its voice
+  pub voice: Option<String>,
+}
+
+#[napi]
+#[derive(Debug, PartialEq, Eq)]
+pub enum MatchKind {
+  /// The word's letters match its phonemes exactly
+  Exact,
+  /// The word's letters roughly match its phonemes
+  Fuzzy,
+  /// A complex token such as a number, matched to the words espeak speaks it as
+  Complex,
+  /// The word was found again after the text and phonemes drifted apart
+  DesyncRecovered,
+  /// The word's times were split from its neighbours' or guessed, as it had no phonemes of its own
+  Interpolated,
+  /// A hyphenated compound or contraction, matched to the words espeak speaks its parts as
+  Compound,
+}
+
+/// Share of a word's confidence kept when its times were interpolated
+const INTERPOLATED_WEIGHT: f64 = 0.5;
+/// Share of a word's confidence kept when it was matched after recovering from a desync
+const DESYNC_RECOVERED_WEIGHT: f64 = 0.75;
+
+#[napi]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub enum AlignAlgorithm {
+  /// Walks words and phoneme words in step, patching desyncs as it finds them
+  #[default]
+  Heuristic,
+  /// Finds the best alignment of the whole text at once with dynamic programming
+  Global,
+}
+
+impl AlignOptions {
+  fn boundaries(&self) -> &BoundaryProfile {
+    self.boundaries.as_ref().unwrap_or(&DEFAULT_BOUNDARIES)
+  }
+
+  fn voice(&self) -> &str {
+    self.voice.as_deref().unwrap_or(DEFAULT_VOICE)
+  }
+
+  /** Options for looking up how tokens are spoken. Tokens are never markup, so SSML is left off */
+  fn phonemize_options(&self) -> PhonemizeOptions {
+    PhonemizeOptions {
+      normalize: self.normalize,
+      boundaries: self.boundaries.clone(),
+      ..Default::default()
+    }
+  }
+
+  fn normalize(&self, text: &str, voice: &str) -> Result<Option<NormalizedText>> {
+    if self.normalize.unwrap_or(false) && !self.ssml.unwrap_or(false) {
+      normalize_for_voice(text, voice).map(Some)
+    } else {
+      Ok(None)
+    }
+  }
+
+  fn group_chunks(&self, text: &str, chunks: &[Chunk]) -> Option<Vec<ParagraphChunk>> {
+    if !self.group_sentences.unwrap_or(false) {
+      return None;
+    }
+    Some(if self.ssml.unwrap_or(false) {
+      group_chunks(text, &mask_markup(text), chunks)
+    } else {
+      group_chunks(text, text, chunks)
+    })
+  }
+}
+
 #[napi]
 pub async fn force_align_phonemes_graphemes(
   text: String,
   phonemes: String,
   align_phonemes: bool,
-) -> NestedChunk {
-  let mut chunk = align_phonemes_graphemes(
-    text.as_str(),
-    (0..phonemes.len()).map(|_| 0.0).collect::<Vec<f64>>(),
-    phonemes
-      .chars()
-      .map(|string| string.to_string())
-      .collect::<Vec<String>>()
-      .iter()
-      .map(|string| string.as_str())
-      .collect(),
-    align_phonemes,
-  )
-  .await;
-  chunk.start_time = chunk
-    .chunks
-    .get(0)
-    .map(|chunk| chunk.start_time)
-    .unwrap_or(0.0);
-  chunk.end_time = chunk
-    .chunks
-    .get(0)
-    .map(|chunk| chunk.end_time)
-    .unwrap_or(0.0);
-  chunk
+  options: Option<AlignOptions>,
+) -> napi::Result<NestedChunk> {
+  let options = options.unwrap_or_default();
+  blocking::run(move || {
+    align_phonemes_graphemes(
+      text.as_str(),
+      (0..phonemes.chars().count())
+        .map(|_| 0.0)
+        .collect::<Vec<f64>>(),
+      phonemes
+        .chars()
+        .map(|string| string.to_string())
+        .collect::<Vec<String>>()
+        .iter()
+        .map(|string| string.as_str())
+        .collect(),
+      align_phonemes,
+      &options,
+    )
+  })
+  .await
 }
 
 #[napi]
@@ -45,34 +159,150 @@ pub async fn force_align_phonemes_graphemes_list(
   end_times: Vec<f64>,
   phonemes_list: Vec<String>,
   align_phonemes: bool,
-) -> NestedChunk {
-  let mut chunk = align_phonemes_graphemes(
-    text.as_str(),
-    end_times,
-    phonemes_list.iter().map(|string| string.as_str()).collect(),
-    align_phonemes,
-  )
-  .await;
-  chunk.start_time = chunk
-    .chunks
-    .get(0)
-    .map(|chunk| chunk.start_time)
-    .unwrap_or(0.0);
-  chunk.end_time = chunk
-    .chunks
-    .get(0)
-    .map(|chunk| chunk.end_time)
-    .unwrap_or(0.0);
-  chunk
+  options: Option<AlignOptions>,
+) -> napi::Result<NestedChunk> {
+  let options = options.unwrap_or_default();
+  blocking::run(move || {
+    align_phonemes_graphemes(
+      text.as_str(),
+      end_times,
+      phonemes_list.iter().map(|string| string.as_str()).collect(),
+      align_phonemes,
+      &options,
+    )
+  })
+  .await
+}
+
+/**
+ * Aligns the text against espeak's own synthesis of it, so the word times are real milliseconds
+ * rather than coming from an external model
+ */
+#[napi]
+pub async fn force_align_synthesized(
+  text: String,
+  voice: Option<String>,
+  options: Option<AlignOptions>,
+) -> napi::Result<NestedChunk> {
+  let voice = voice.unwrap_or_else(|| DEFAULT_VOICE.to_string());
+  let options = options.unwrap_or_default();
+  blocking::run(move || align_synthesized(text.as_str(), &voice, &options)).await
+}
+
+pub fn align_synthesized(text: &str, voice: &str, options: &AlignOptions) -> Result<NestedChunk> {
+  if text.trim().is_empty() {
+    return Err(Error::EmptyInput("text"));
+  }
+
+  let normalized = options.normalize(text, voice)?;
+  let voice = voice.to_string();
+  let spoken = normalized
+    .as_ref()
+    .map(|normalized| normalized.text.as_str())
+    .unwrap_or(text);
+  let synthesis_text = spoken.to_string();
+  let flags = if options.ssml.unwrap_or(false) {
+    bindings::espeakSSML
+  } else {
+    0
+  };
+  let synthesis = speaker::run(move |session| {
+    session.set_voice(&voice)?;
+    synthesize_with_events(session, &synthesis_text, flags)
+  })?;
+
+  let include_phonemes = options.include_phonemes.unwrap_or(false);
+  let mut chunks: Vec<Chunk> = Vec::new();
+  // Phonemes are indexed in the order espeak spoke them, leaving out pauses
+  let mut phoneme_index = 0;
+  for (index, event) in synthesis.events.iter().enumerate() {
+    match &event.kind {
+      // espeak spoke the words itself, so every match is exact
+      SynthesisEventKind::Word => chunks.push(Chunk {
+        value: spoken
+          .chars()
+          .skip(event.text_position)
+          .take(event.length)
+          .collect(),
+        start: event.text_position as f64,
+        end: (event.text_position + event.length) as f64,
+        start_time: event.audio_position,
+        end_time: synthesis.event_end_time(index),
+        confidence: Some(1.0),
+        match_kind: Some(MatchKind::Exact),
+        ..Default::default()
+      }),
+      SynthesisEventKind::Phoneme(name) if !name.starts_with('_') => {
+        if let Some(word) = chunks.last_mut().filter(|_| include_phonemes) {
+          word.phonemes.get_or_insert_with(String::new).push_str(name);
+          word.phoneme_start.get_or_insert(phoneme_index as f64);
+          word.phoneme_end = Some((phoneme_index + 1) as f64);
+          word
+            .phoneme_chunks
+            .get_or_insert_with(Vec::new)
+            .push(Chunk {
+              value: name.clone(),
+              start: phoneme_index as f64,
+              end: (phoneme_index + 1) as f64,
+              start_time: event.audio_position,
+              end_time: synthesis
+                .events
+                .get(index + 1)
+                .map(|next| next.audio_position)
+                .unwrap_or_else(|| synthesis.duration()),
+              ..Default::default()
+            });
+        }
+        phoneme_index += 1;
+      }
+      _ => {}
+    }
+  }
+  let chunks = match &normalized {
+    Some(normalized) => restore_offsets(text, normalized, chunks),
+    None => chunks,
+  };
+
+  let mut chunk = NestedChunk {
+    value: text.to_string(),
+    start: 0.0,
+    end: text.chars().count() as f64,
+    start_time: chunks.first().map(|chunk| chunk.start_time).unwrap_or(0.0),
+    end_time: synthesis.duration(),
+    quality: quality(&chunks),
+    paragraphs: options.group_chunks(text, &chunks),
+    chunks,
+  };
+  convert_offsets(text, options.offset_unit.unwrap_or_default(), &mut chunk);
+  Ok(chunk)
 }
 
-pub async fn align_phonemes_graphemes(
+pub fn align_phonemes_graphemes(
   text: &str,
   end_times: Vec<f64>,
   phonemes_list: Vec<&str>,
   align_phonemes: bool,
-) -> NestedChunk {
-  let phonemes = transform_raw_phoneme_timestamps(&phonemes_list, &end_times);
+  options: &AlignOptions,
+) -> Result<NestedChunk> {
+  if text.trim().is_empty() {
+    return Err(Error::EmptyInput("text"));
+  }
+  if phonemes_list.is_empty() {
+    return Err(Error::EmptyInput("phonemes"));
+  }
+  if end_times.len() != phonemes_list.len() {
+    return Err(Error::LengthMismatch {
+      end_times: end_times.len(),
+      phonemes: phonemes_list.len(),
+    });
+  }
+
+  let phonemes = transform_raw_phoneme_timestamps(
+    &phonemes_list,
+    &end_times,
+    options.boundaries(),
+    options.voice(),
+  );
 
   let chunk: NestedChunk = NestedChunk {
     value: if align_phonemes {
@@ -89,13 +319,85 @@ pub async fn align_phonemes_graphemes(
       text.chars().count() as f64
     },
     chunks: Vec::new(),
+    quality: None,
+    paragraphs: None,
+  };
+
+  // Phoneme alignment points chunks into the phonemes, so there's no text to map back to
+  let normalized = if align_phonemes {
+    None
+  } else {
+    options.normalize(text, options.voice())?
+  };
+  let segmentation = options.segmentation.unwrap_or_default();
+  let words: Vec<Chunk> = if options.ssml.unwrap_or(false) {
+    split_words(&mask_markup(text), segmentation, options.boundaries())
+  } else if let Some(normalized) = &normalized {
+    split_words(&normalized.text, segmentation, options.boundaries())
+  } else {
+    split_words(text, segmentation, options.boundaries())
+  };
+
+  let tokens = Tokens::new(&words, options)?;
+  let mut chunks = match options.algorithm.unwrap_or_default() {
+    AlignAlgorithm::Heuristic => align_heuristically(
+      words,
+      &tokens,
+      &phonemes,
+      &phonemes_list,
+      &end_times,
+      align_phonemes,
+      options,
+    )?,
+    AlignAlgorithm::Global => align_globally(
+      &words,
+      &tokens,
+      &phonemes,
+      &phonemes_list,
+      &end_times,
+      align_phonemes,
+      options,
+    )?,
   };
+  if let Some(normalized) = &normalized {
+    chunks = restore_offsets(text, normalized, chunks);
+  }
 
+  let mut chunk = NestedChunk {
+    value: chunk.value,
+    start: chunk.start,
+    end: chunk.end,
+    // The text spans from its first word to its last
+    start_time: chunks.first().map(|chunk| chunk.start_time).unwrap_or(0.0),
+    end_time: chunks.last().map(|chunk| chunk.end_time).unwrap_or(0.0),
+    quality: quality(&chunks),
+    paragraphs: if align_phonemes {
+      None
+    } else {
+      options.group_chunks(text, &chunks)
+    },
+    chunks,
+  };
+  // Phoneme alignment offsets index the phoneme list, which has no other units
+  if !align_phonemes {
+    convert_offsets(text, options.offset_unit.unwrap_or_default(), &mut chunk);
+  }
+  Ok(chunk)
+}
+
+/** The legacy aligner, walking words and phoneme words in step and patching desyncs as it goes */
+fn align_heuristically(
+  mut words: Vec<Chunk>,
+  tokens: &Tokens,
+  phonemes: &[PhonemeChunk],
+  phonemes_list: &[&str],
+  end_times: &[f64],
+  align_phonemes: bool,
+  options: &AlignOptions,
+) -> Result<Vec<Chunk>> {
   let mut chunks: Vec<Chunk> = Vec::new();
 
   let mut phoneme_index = 0;
-  let mut words: Vec<Chunk> = split_text_to_word_chunks(text);
-
   for word_index in 0..words.len() {
     let word = words.get_mut(word_index).unwrap();
     if phoneme_index >= phonemes.len() {
@@ -104,13 +406,12 @@ pub async fn align_phonemes_graphemes(
     let phoneme = phonemes.get(phoneme_index).unwrap();
 
     let mut word = word.clone();
+    let first_phoneme_index = phoneme_index;
+    let mut kind = MatchKind::Fuzzy;
+    let mut expected = None;
 
     // Fill word with data
-    word.start_time = chunk
-      .chunks
-      .last()
-      .map(|chunk| chunk.end_time)
-      .unwrap_or(phonemes[phoneme_index].start_time);
+    word.start_time = phonemes[phoneme_index].start_time;
     word.end_time = phonemes[phoneme_index].end_time;
 
     if align_phonemes {
@@ -120,7 +421,7 @@ pub async fn align_phonemes_graphemes(
 
     let is_desync_detected = get_average_leven(
       &words,
-      &phonemes,
+      phonemes,
       word_index,
       phoneme_index,
       usize::min(3, words.len() - word_index - 1).min(phonemes.len() - phoneme_index - 1),
@@ -128,17 +429,17 @@ pub async fn align_phonemes_graphemes(
     ) > 0.6;
 
     // Handle numbers. 2021 -> two thousand twenty one and anything else
-    // that isComplex deems as too complicated for regular handling
-    if is_complex(word.value.as_str()) {
-      // Convert to phonemes and check how many words it was
-      let word_phonemes = text_to_phonemes(word.value.as_str());
-      let word_phoneme_word_count = usize::max(
-        1,
-        word_phonemes
-          .split(' ')
-          .filter(|string| !string.is_empty())
-          .count(),
-      );
+    // that isComplex deems as too complicated for regular handling. Compounds
+    // such as state-of-the-art are spoken as several words in the same way
+    let parts = &tokens.parts[word_index];
+    if let Some(spoken) = &tokens.spoken[word_index] {
+      let word_phoneme_word_count = usize::max(1, spoken.len());
+      kind = if parts.is_empty() {
+        MatchKind::Complex
+      } else {
+        MatchKind::Compound
+      };
+      expected = Some(spoken);
 
       phoneme_index += word_phoneme_word_count - 1;
       word.end_time = phonemes
@@ -153,86 +454,491 @@ pub async fn align_phonemes_graphemes(
     else if is_desync_detected {
       // More accurately detect desyncing
       let offset =
-        get_closest_correct_word_offset(&words, &phonemes, word_index, phoneme_index, 3, 8);
-
-      if offset > 0 && word_index > 0 {
-        let previous_word = words.get_mut(word_index - 1).unwrap();
+        get_closest_correct_word_offset(&words, phonemes, word_index, phoneme_index, 3, 8);
 
+      let previous_word = chunks.last_mut().filter(|_| phoneme_index > 0);
+      if let (true, Some(previous_word)) = (offset > 0, previous_word) {
+        // The word wasn't spoken on its own, so it splits the previous word's time
         let start_time = previous_word.start_time;
         let end_time = previous_word.end_time;
         let middle_time = (end_time - start_time) / 2.0 + start_time;
 
         previous_word.end_time = middle_time;
+        interpolate(previous_word);
         word.start_time = middle_time;
         word.end_time = end_time;
         phoneme_index -= 1;
+        kind = MatchKind::Interpolated;
       } else if offset < 0 {
         phoneme_index += 1;
         word.end_time = phonemes[phoneme_index - 1].end_time;
+        kind = MatchKind::DesyncRecovered;
       }
     }
 
+    let last_phoneme_index = usize::min(phoneme_index, phonemes.len() - 1);
+    let matched = &phonemes[first_phoneme_index.min(last_phoneme_index)..=last_phoneme_index];
+    let similarity = match &expected {
+      Some(expected) => complex_similarity(expected, matched),
+      None => similarity(&word, matched),
+    };
+    score(&mut word, kind, similarity);
+    word.parts = align_parts(
+      parts,
+      matched,
+      phonemes_list,
+      end_times,
+      align_phonemes,
+      options,
+    )?;
+
     if align_phonemes {
       word.value = phonemes_list[(word.start as usize)..(word.end as usize)].join("");
     }
 
+    if options.include_phonemes.unwrap_or(false) {
+      attach_phonemes(
+        &mut word,
+        matched,
+        phonemes_list,
+        end_times,
+        options.boundaries(),
+      );
+    }
+
     phoneme_index += 1;
 
     chunks.push(word);
   }
 
-  NestedChunk {
-    value: chunk.value,
-    start: chunk.start,
-    end: chunk.end,
-    start_time: chunk.start_time,
-    end_time: chunk.end_time,
-    chunks,
+  Ok(chunks)
+}
+
+/**
+ * Aligns the whole text at once, matching words to the phoneme words that minimise the total
+ * distance between them. Words left unspoken get no time of their own, and words matched to
+ * the same phoneme word share its time
+ */
+fn align_globally(
+  words: &[Chunk],
+  tokens: &Tokens,
+  phonemes: &[PhonemeChunk],
+  phonemes_list: &[&str],
+  end_times: &[f64],
+  align_phonemes: bool,
+  options: &AlignOptions,
+) -> Result<Vec<Chunk>> {
+  let Tokens { parts, spoken } = tokens;
+  let spans = spoken
+    .iter()
+    .map(|spoken| spoken.as_ref().map(|spoken| spoken.len().max(1)))
+    .collect::<Vec<_>>();
+
+  let mut chunks: Vec<Chunk> = Vec::new();
+  let mut time = phonemes
+    .first()
+    .map(|phoneme| phoneme.start_time)
+    .unwrap_or(0.0);
+  let mut phoneme_position = phonemes.first().map(|phoneme| phoneme.start).unwrap_or(0.0);
+  for step in align_sequences(words, phonemes, &spans) {
+    let matched = &phonemes[step.phonemes.clone()];
+    let (first, last) = match (matched.first(), matched.last()) {
+      (Some(first), Some(last)) => (first, last),
+      _ => {
+        for word in &words[step.words] {
+          let mut word = word.clone();
+          word.start_time = time;
+          word.end_time = time;
+          score(&mut word, MatchKind::Interpolated, 0.0);
+          if align_phonemes {
+            word.value = String::new();
+            word.start = phoneme_position;
+            word.end = phoneme_position;
+          }
+          chunks.push(word);
+        }
+        continue;
+      }
+    };
+
+    let share = (last.end_time - first.start_time) / step.words.len().max(1) as f64;
+    let (kind, similarity) = match &spoken[step.words.start] {
+      _ if step.words.len() > 1 => {
+        let merged = Chunk {
+          value: words[step.words.clone()]
+            .iter()
+            .map(|word| word.value.as_str())
+            .collect(),
+          ..Default::default()
+        };
+        (MatchKind::Interpolated, similarity(&merged, matched))
+      }
+      Some(expected) if spans[step.words.start] == Some(matched.len()) => {
+        let kind = if parts[step.words.start].is_empty() {
+          MatchKind::Complex
+        } else {
+          MatchKind::Compound
+        };
+        (kind, complex_similarity(expected, matched))
+      }
+      _ => (
+        MatchKind::Fuzzy,
+        similarity(&words[step.words.start], matched),
+      ),
+    };
+    for (index, word) in words[step.words.clone()].iter().enumerate() {
+      let mut word = word.clone();
+      word.start_time = first.start_time + share * index as f64;
+      word.end_time = first.start_time + share * (index + 1) as f64;
+      score(&mut word, kind, similarity);
+      if step.words.len() == 1 {
+        word.parts = align_parts(
+          &parts[step.words.start],
+          matched,
+          phonemes_list,
+          end_times,
+          align_phonemes,
+          options,
+        )?;
+      }
+      if align_phonemes {
+        word.start = first.start;
+        word.end = last.end;
+        word.value = phonemes_list[(word.start as usize)..(word.end as usize)].join("");
+      }
+      if options.include_phonemes.unwrap_or(false) {
+        attach_phonemes(
+          &mut word,
+          matched,
+          phonemes_list,
+          end_times,
+          options.boundaries(),
+        );
+      }
+      chunks.push(word);
+    }
+    time = last.end_time;
+    phoneme_position = last.end;
   }
+
+  Ok(chunks)
 }
 
-fn split_text_to_word_chunks(text: &str) -> Vec<Chunk> {
-  // TODO: Replace with reduce or map with accumulator
-  let mut character_counter = 0;
-  let ignored_chars = [',', '.', '?', '!', ':', '[', ']', '{', '}', '"', ' ', '\n'];
+/**
+ * Aligns the parts of a compound to the phoneme words the whole compound was matched to, so
+ * each part gets its own times. Parts can share a phoneme word, as "'s" does, or span several
+ */
+fn align_parts(
+  parts: &[Chunk],
+  matched: &[PhonemeChunk],
+  phonemes_list: &[&str],
+  end_times: &[f64],
+  align_phonemes: bool,
+  options: &AlignOptions,
+) -> Result<Option<Vec<Chunk>>> {
+  if parts.is_empty() || matched.is_empty() {
+    return Ok(None);
+  }
+  // Parts are never complex, as any complex part makes the whole word complex
+  align_globally(
+    parts,
+    &Tokens::plain(parts.len()),
+    matched,
+    phonemes_list,
+    end_times,
+    align_phonemes,
+    options,
+  )
+  .map(Some)
+}
 
-  return text
-    .split_inclusive(ignored_chars)
-    .filter_map(|word| -> Option<Chunk> {
-      // We use .chars().count() to get the number of characters rather than
-      // the number of bytes
-      let full_word_len = word.chars().count();
+/**
+ * Whether the word is too complicated to compare against phonemes letter by letter, such as a
+ * number. Lexicon overrides are spoken as a single word however complex they look
+ */
+fn is_complex_token(word: &str) -> bool {
+  is_complex(word) && !lexicon::lexicon().covers(word)
+}
 
-      // Remove the punctuation the end
-      let trimmed_word = word
-        .chars()
-        .filter(|char| !ignored_chars.contains(char))
-        .collect::<String>();
-      let trimmed_word_len = trimmed_word.chars().count();
+/**
+ * The phonemes of each word espeak speaks each token as, such as 2021 as "two thousand twenty
+ * one". Tokens are read in a single synthesis, so a text full of them costs one speaker job
+ */
+fn spoken_words(tokens: &[&str], options: &AlignOptions) -> Result<Vec<Vec<String>>> {
+  if tokens.is_empty() {
+    return Ok(Vec::new());
+  }
 
-      let start = character_counter;
-      let end = character_counter + trimmed_word_len;
+  // Commas keep neighbouring tokens from being read together, such as "1" and "000"
+  let mut text = String::new();
+  let mut ranges = Vec::with_capacity(tokens.len());
+  for token in tokens {
+    if !text.is_empty() {
+      text.push_str(", ");
+    }
+    let start = text.chars().count();
+    text.push_str(token);
+    ranges.push(start..text.chars().count());
+  }
+
+  let words = phonemize_words(&text, options.voice(), &options.phonemize_options())?;
+  Ok(
+    ranges
+      .iter()
+      .map(|range| {
+        words
+          .iter()
+          .filter(|word| range.contains(&(word.start as usize)))
+          .map(|word| word.phonemes.clone())
+          .collect()
+      })
+      .collect(),
+  )
+}
 
-      // Track the number of chars we've reached
-      if word != "CS" {
-        character_counter += full_word_len
+/** The parts of each compound, and the phoneme words each complex token or compound is spoken as */
+struct Tokens {
+  parts: Vec<Vec<Chunk>>,
+  spoken: Vec<Option<Vec<String>>>,
+}
+
+impl Tokens {
+  fn new(words: &[Chunk], options: &AlignOptions) -> Result<Tokens> {
+    let mut parts = Vec::with_capacity(words.len());
+    let mut lookups: Vec<&str> = Vec::new();
+    let mut indices = Vec::with_capacity(words.len());
+    for word in words {
+      let is_complex = is_complex_token(word.value.as_str());
+      let compound = if is_complex {
+        Vec::new()
+      } else {
+        split_compound(word, options.boundaries())
       };
+      // Repeated tokens, such as "don't", are only looked up once
+      indices.push((is_complex || !compound.is_empty()).then(|| {
+        lookups
+          .iter()
+          .position(|token| *token == word.value)
+          .unwrap_or_else(|| {
+            lookups.push(&word.value);
+            lookups.len() - 1
+          })
+      }));
+      parts.push(compound);
+    }
 
-      // If it's not a word, continue
-      if !is_word(trimmed_word.as_str()) {
-        return None;
-      }
+    let spoken = spoken_words(&lookups, options)?;
+    Ok(Tokens {
+      parts,
+      spoken: indices
+        .into_iter()
+        .map(|index| index.map(|index| spoken[index].clone()))
+        .collect(),
+    })
+  }
+
+  /** Tokens for words which are neither complex nor compounds */
+  fn plain(count: usize) -> Tokens {
+    Tokens {
+      parts: vec![Vec::new(); count],
+      spoken: vec![None; count],
+    }
+  }
+}
+
+/** How closely the word's letters match the phonemes it was aligned to, from 0 to 1 */
+fn similarity(word: &Chunk, matched: &[PhonemeChunk]) -> f64 {
+  let phonemes = matched
+    .iter()
+    .map(|phoneme| phoneme.value.as_str())
+    .collect::<String>();
+  (1.0 - leven_phoneme_relative(word, &phonemes) as f64).clamp(0.0, 1.0)
+}
+
+/** How closely the phonemes espeak speaks a complex token as match those it was aligned to */
+fn complex_similarity(expected: &[String], matched: &[PhonemeChunk]) -> f64 {
+  // Phoneme lists from other models may mark stress where espeak's words don't
+  let strip = |phonemes: &str| {
+    phonemes
+      .chars()
+      .filter(|char| !char.is_whitespace() && !matches!(char, 'ˈ' | 'ˌ'))
+      .collect::<String>()
+  };
+  let expected = strip(&expected.concat());
+  let matched = strip(
+    &matched
+      .iter()
+      .map(|phoneme| phoneme.value.as_str())
+      .collect::<String>(),
+  );
+  let length = expected.chars().count().max(matched.chars().count()).max(1);
+  1.0 - levenshtein(&expected, &matched) as f64 / length as f64
+}
+
+/** Records how the word was matched, promoting fuzzy matches with identical phonemes to exact */
+fn score(word: &mut Chunk, kind: MatchKind, similarity: f64) {
+  let kind = match kind {
+    MatchKind::Fuzzy if similarity >= 1.0 => MatchKind::Exact,
+    kind => kind,
+  };
+  let weight = match kind {
+    MatchKind::Interpolated => INTERPOLATED_WEIGHT,
+    MatchKind::DesyncRecovered => DESYNC_RECOVERED_WEIGHT,
+    MatchKind::Exact | MatchKind::Fuzzy | MatchKind::Complex | MatchKind::Compound => 1.0,
+  };
+  word.confidence = Some(similarity.clamp(0.0, 1.0) * weight);
+  word.match_kind = Some(kind);
+}
+
+/** Marks an already scored word as having had its times interpolated */
+fn interpolate(word: &mut Chunk) {
+  if word.match_kind != Some(MatchKind::Interpolated) {
+    word.confidence = word
+      .confidence
+      .map(|confidence| confidence * INTERPOLATED_WEIGHT);
+    word.match_kind = Some(MatchKind::Interpolated);
+  }
+}
 
-      // Otherwise, add the word to our list
-      Some(Chunk {
-        value: trimmed_word,
+/** Overall quality of an alignment, as the mean confidence of its words */
+fn quality(chunks: &[Chunk]) -> Option<f64> {
+  let confidences = chunks
+    .iter()
+    .filter_map(|chunk| chunk.confidence)
+    .collect::<Vec<_>>();
+  if confidences.is_empty() {
+    return None;
+  }
+  Some(confidences.iter().sum::<f64>() / confidences.len() as f64)
+}
+
+/**
+ * Points chunks aligned against normalized text back at the original text, merging the words an
+ * expansion was spoken as into a single chunk for its token
+ */
+fn restore_offsets(text: &str, normalized: &NormalizedText, chunks: Vec<Chunk>) -> Vec<Chunk> {
+  let chars = text.chars().collect::<Vec<_>>();
+  let mut restored: Vec<Chunk> = Vec::new();
+  for mut chunk in chunks {
+    let (start, end) = normalized.original_range(chunk.start as usize, chunk.end as usize);
+    for part in chunk.parts.iter_mut().flatten() {
+      let (start, end) = normalized.original_range(part.start as usize, part.end as usize);
+      part.value = chars[start..end].iter().collect();
+      part.start = start as f64;
+      part.end = end as f64;
+    }
+    match restored.last_mut() {
+      Some(last) if (start as f64) < last.end => {
+        last.end = last.end.max(end as f64);
+        last.end_time = chunk.end_time;
+        if let Some(parts) = chunk.parts {
+          last.parts.get_or_insert_with(Vec::new).extend(parts);
+        }
+        last.value = chars[last.start as usize..last.end as usize]
+          .iter()
+          .collect();
+        // A token is only as well aligned as its worst matched word
+        if chunk.confidence < last.confidence {
+          last.confidence = chunk.confidence;
+          last.match_kind = chunk.match_kind;
+        }
+
+        // Words sharing a phoneme word after a desync would otherwise repeat its phonemes
+        if chunk.phoneme_end > last.phoneme_end {
+          if let (Some(phonemes), Some(next)) = (last.phonemes.as_mut(), chunk.phonemes) {
+            phonemes.push(' ');
+            phonemes.push_str(&next);
+          }
+          if let (Some(phoneme_chunks), Some(next)) =
+            (last.phoneme_chunks.as_mut(), chunk.phoneme_chunks)
+          {
+            let last_end = last.phoneme_end.unwrap_or(0.0);
+            phoneme_chunks.extend(next.into_iter().filter(|phoneme| phoneme.start >= last_end));
+          }
+          last.phoneme_end = chunk.phoneme_end;
+        }
+      }
+      _ => restored.push(Chunk {
+        value: chars[start..end].iter().collect(),
         start: start as f64,
-        start_time: 0.0,
         end: end as f64,
-        end_time: 0.0,
+        ..chunk
+      }),
+    }
+  }
+  restored
+}
+
+/** Fills in the phonemes matched to the word, with a chunk for each phoneme in the list */
+fn attach_phonemes(
+  word: &mut Chunk,
+  matched: &[PhonemeChunk],
+  phonemes_list: &[&str],
+  end_times: &[f64],
+  boundaries: &BoundaryProfile,
+) {
+  let start = matched.first().map(|phoneme| phoneme.start).unwrap_or(0.0) as usize;
+  let end = matched.last().map(|phoneme| phoneme.end).unwrap_or(0.0) as usize;
+
+  word.phonemes = Some(
+    matched
+      .iter()
+      .map(|phoneme| phoneme.value.as_str())
+      .collect::<Vec<_>>()
+      .join(" "),
+  );
+  word.phoneme_start = Some(start as f64);
+  word.phoneme_end = Some(end as f64);
+  word.phoneme_chunks = Some(
+    (start..end.min(phonemes_list.len()))
+      .filter(|index| {
+        !is_phonetic_word_boundary(phonemes_list[*index], boundaries)
+          && !PHONETIC_WORD_TAG_BOUNDARIES.contains(&phonemes_list[*index])
+      })
+      .map(|index| Chunk {
+        value: phonemes_list[index].to_string(),
+        start: index as f64,
+        end: (index + 1) as f64,
+        start_time: if index == 0 {
+          0.0
+        } else {
+          end_times[index - 1] * 1000.0
+        },
+        end_time: end_times[index] * 1000.0,
+        ..Default::default()
       })
+      .collect(),
+  );
+}
+
+pub static REGEX_SSML_ENTITY: Lazy<Regex> =
+  Lazy::new(|| Regex::new("&(?:#[0-9]+|#[xX][0-9a-fA-F]+|[A-Za-z]+);").unwrap());
+
+/**
+ * Blanks out SSML tags and entities such as `&amp;` with spaces, so words can be split from the
+ * spoken text while their offsets still point into the original SSML
+ */
+fn mask_markup(text: &str) -> String {
+  // Entities are ASCII, so each is blanked with as many spaces as it has chars
+  let text = REGEX_SSML_ENTITY.replace_all(text, |captures: &regex::Captures| {
+    " ".repeat(captures[0].len())
+  });
+  let mut in_tag = false;
+  text
+    .chars()
+    .map(|char| match char {
+      '<' => {
+        in_tag = true;
+        ' '
+      }
+      '>' if in_tag => {
+        in_tag = false;
+        ' '
+      }
+      _ if in_tag => ' ',
+      _ => char,
     })
-    .collect();
+    .collect()
 }
 
 pub static REGEX_COMPLEX_ACRONYM: Lazy<Regex> =
@@ -253,7 +959,7 @@ fn is_complex(string: &str) -> bool {
 
 // Originally used \p{Sc} but rust doesnt have support so pulled it from
 // https://www.compart.com/en/unicode/category/Sc
-fn is_word(word: &str) -> bool {
+pub(crate) fn is_word(word: &str) -> bool {
   REGEX_WORD.is_match(word)
 }
 
@@ -263,8 +969,8 @@ fn is_word(word: &str) -> bool {
  * and right indices until the maxOffset is reached
  */
 fn get_closest_correct_word_offset(
-  words: &Vec<Chunk>,
-  phonemes: &Vec<PhonemeChunk>,
+  words: &[Chunk],
+  phonemes: &[PhonemeChunk],
   word_index: usize,
   phoneme_index: usize,
   max_offset: usize,
@@ -318,3 +1024,268 @@ fn get_closest_correct_word_offset(
     .position(|a| a == lowest_leven_value)
     .unwrap()]
 }
+
+#[cfg(test)]
+mod tests {
+  use super::{
+    align_parts, align_phonemes_graphemes, interpolate, quality, score, AlignAlgorithm,
+    AlignOptions, MatchKind,
+  };
+  use crate::boundary::DEFAULT_BOUNDARIES;
+  use crate::voice::DEFAULT_VOICE;
+  use crate::{transform_raw_phoneme_timestamps, Chunk, NestedChunk};
+
+  /** A phoneme list of the phoneme words, each phoneme taking 10ms and each space 50ms */
+  fn phoneme_list(phoneme_words: &[&str]) -> (Vec<String>, Vec<f64>) {
+    let phonemes_list = phoneme_words
+      .join(" ")
+      .chars()
+      .map(|char| char.to_string())
+      .collect::<Vec<_>>();
+    let mut time = 0.0;
+    let end_times = phonemes_list
+      .iter()
+      .map(|phoneme| {
+        time += if phoneme == " " { 0.05 } else { 0.01 };
+        time
+      })
+      .collect();
+    (phonemes_list, end_times)
+  }
+
+  fn align(text: &str, phoneme_words: &[&str], options: &AlignOptions) -> NestedChunk {
+    let (phonemes_list, end_times) = phoneme_list(phoneme_words);
+    let phonemes_list = phonemes_list
+      .iter()
+      .map(|phoneme| phoneme.as_str())
+      .collect();
+    align_phonemes_graphemes(text, end_times, phonemes_list, false, options).unwrap()
+  }
+
+  fn chunk(value: &str, start: f64) -> Chunk {
+    Chunk {
+      value: value.to_string(),
+      start,
+      end: start + value.chars().count() as f64,
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn test_desync_interpolation() {
+    // "extra" isn't spoken, so the aligner falls behind and "sat" shares "cat"'s phonemes
+    let text = "the big extra cat sat on the warm mat and then it slept well all day long";
+    let phonemes = [
+      "ðə", "bɪɡ", "kæt", "sæt", "ɒn", "ðə", "wɔːm", "mæt", "ænd", "ðɛn", "ɪt", "slɛpt", "wɛl",
+      "ɔːl", "deɪ", "lɒŋ",
+    ];
+    let chunks = align(text, &phonemes, &AlignOptions::default()).chunks;
+    let (cat, sat) = (&chunks[3], &chunks[4]);
+
+    // Both words sharing the time are marked, and they don't overlap
+    assert_eq!((cat.value.as_str(), sat.value.as_str()), ("cat", "sat"));
+    assert_eq!(cat.match_kind, Some(MatchKind::Interpolated));
+    assert_eq!(sat.match_kind, Some(MatchKind::Interpolated));
+    assert_eq!(cat.end_time, sat.start_time);
+    assert!(cat.start_time < cat.end_time && sat.start_time < sat.end_time);
+  }
+
+  #[test]
+  fn test_group_sentences() {
+    let options = AlignOptions {
+      group_sentences: Some(true),
+      ..Default::default()
+    };
+    let chunk = align(
+      "The cat sat. It slept.",
+      &["ðə", "kæt", "sæt", "ɪt", "slɛpt"],
+      &options,
+    );
+    let paragraphs = chunk.paragraphs.unwrap();
+    let sentences = &paragraphs[0].sentences;
+
+    assert_eq!(sentences.len(), 2);
+    assert_eq!(sentences[0].chunks.len(), 3);
+    assert_eq!(chunk.start_time, chunk.chunks[0].start_time);
+    assert_eq!(chunk.end_time, sentences[1].end_time);
+    assert_eq!(chunk.end_time, chunk.chunks[4].end_time);
+  }
+
+  #[test]
+  fn test_include_phonemes() {
+    let options = AlignOptions {
+      include_phonemes: Some(true),
+      ..Default::default()
+    };
+    let chunks = align("The cat", &["ðə", "kæt"], &options).chunks;
+    let cat = &chunks[1];
+
+    // Phoneme ranges index the list, skipping the space between words
+    assert_eq!(chunks[0].phonemes.as_deref(), Some("ðə"));
+    assert_eq!(
+      (chunks[0].phoneme_start, chunks[0].phoneme_end),
+      (Some(0.0), Some(2.0))
+    );
+    assert_eq!((cat.phoneme_start, cat.phoneme_end), (Some(3.0), Some(6.0)));
+    let phoneme_chunks = cat.phoneme_chunks.as_ref().unwrap();
+    assert_eq!(
+      phoneme_chunks
+        .iter()
+        .map(|phoneme| (phoneme.value.as_str(), phoneme.start, phoneme.end))
+        .collect::<Vec<_>>(),
+      vec![("k", 3.0, 4.0), ("æ", 4.0, 5.0), ("t", 5.0, 6.0)]
+    );
+    assert_eq!(phoneme_chunks[0].start_time, cat.start_time);
+    assert_eq!(phoneme_chunks[2].end_time, cat.end_time);
+  }
+
+  #[test]
+  fn test_global_alignment() {
+    let options = AlignOptions {
+      algorithm: Some(AlignAlgorithm::Global),
+      ..Default::default()
+    };
+    let chunks = align("the big extra cat", &["ðə", "bɪɡ", "kæt"], &options).chunks;
+
+    // The unspoken word takes no time, between the words either side of it
+    let (big, extra, cat) = (&chunks[1], &chunks[2], &chunks[3]);
+    assert_eq!(extra.match_kind, Some(MatchKind::Interpolated));
+    assert_eq!(extra.confidence, Some(0.0));
+    assert_eq!(
+      (extra.start_time, extra.end_time),
+      (big.end_time, big.end_time)
+    );
+    assert_eq!(cat.match_kind, Some(MatchKind::Exact));
+    assert!(cat.start_time > big.end_time);
+  }
+
+  #[test]
+  fn test_score() {
+    let mut word = Chunk::default();
+    score(&mut word, MatchKind::Fuzzy, 1.0);
+    assert_eq!(
+      (word.match_kind, word.confidence),
+      (Some(MatchKind::Exact), Some(1.0))
+    );
+    score(&mut word, MatchKind::DesyncRecovered, 0.8);
+    assert_eq!(word.match_kind, Some(MatchKind::DesyncRecovered));
+    assert!((word.confidence.unwrap() - 0.6).abs() < 1e-9);
+
+    // Interpolating only weighs a word down once
+    score(&mut word, MatchKind::Fuzzy, 0.5);
+    interpolate(&mut word);
+    interpolate(&mut word);
+    assert_eq!(
+      (word.match_kind, word.confidence),
+      (Some(MatchKind::Interpolated), Some(0.25))
+    );
+    assert_eq!(quality(&[word, Chunk::default()]), Some(0.25));
+    assert_eq!(quality(&[]), None);
+  }
+
+  #[test]
+  fn test_align_parts() {
+    let (phonemes_list, end_times) = phoneme_list(&["wɛl", "noʊn", "dʒɑːnz"]);
+    let phonemes_list = phonemes_list
+      .iter()
+      .map(|phoneme| phoneme.as_str())
+      .collect::<Vec<_>>();
+    let matched = transform_raw_phoneme_timestamps(
+      &phonemes_list,
+      &end_times,
+      &DEFAULT_BOUNDARIES,
+      DEFAULT_VOICE,
+    );
+    let options = AlignOptions::default();
+
+    // Each part of "well-known" gets its own phoneme word
+    let parts = align_parts(
+      &[chunk("well", 0.0), chunk("known", 5.0)],
+      &matched[..2],
+      &phonemes_list,
+      &end_times,
+      false,
+      &options,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(
+      parts
+        .iter()
+        .map(|part| (part.start_time, part.end_time))
+        .collect::<Vec<_>>(),
+      matched[..2]
+        .iter()
+        .map(|phoneme| (phoneme.start_time, phoneme.end_time))
+        .collect::<Vec<_>>()
+    );
+
+    // The parts of "John's" share its one phoneme word, splitting its time
+    let parts = align_parts(
+      &[chunk("John", 0.0), chunk("'s", 4.0)],
+      &matched[2..],
+      &phonemes_list,
+      &end_times,
+      false,
+      &options,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(parts[0].start_time, matched[2].start_time);
+    assert_eq!(parts[0].end_time, parts[1].start_time);
+    assert_eq!(parts[1].end_time, matched[2].end_time);
+    assert_eq!(parts[1].match_kind, Some(MatchKind::Interpolated));
+
+    assert!(
+      align_parts(&[], &matched, &phonemes_list, &end_times, false, &options)
+        .unwrap()
+        .is_none()
+    );
+  }
+
+  #[test]
+  fn test_normalized_offsets() {
+    let options = AlignOptions {
+      normalize: Some(true),
+      ..Default::default()
+    };
+    let phonemes = ["aɪ", "ɹæn", "twɛnti", "wʌn", "kɪlɑːmɪɾɚz"];
+    let chunks = align("I ran 21 km", &phonemes, &options).chunks;
+
+    // The words "21 km" was spoken as merge back into one chunk for the token
+    assert_eq!(
+      chunks
+        .iter()
+        .map(|chunk| (chunk.value.as_str(), chunk.start, chunk.end))
+        .collect::<Vec<_>>(),
+      vec![("I", 0.0, 1.0), ("ran", 2.0, 5.0), ("21 km", 6.0, 11.0)]
+    );
+    // and spans from the first of those words to the last
+    let (_, end_times) = phoneme_list(&phonemes);
+    assert!(chunks[1].end_time < chunks[2].start_time);
+    assert_eq!(chunks[2].end_time, end_times.last().unwrap() * 1000.0);
+  }
+
+  #[test]
+  fn test_ssml() {
+    let options = AlignOptions {
+      ssml: Some(true),
+      ..Default::default()
+    };
+    let chunks = align(
+      "<speak>Tom &amp; <emphasis>the</emphasis> cat&#39;</speak>",
+      &["tɑːm", "ðə", "kæt"],
+      &options,
+    )
+    .chunks;
+
+    // Tags and entities aren't words, and offsets still point into the markup
+    assert_eq!(
+      chunks
+        .iter()
+        .map(|chunk| (chunk.value.as_str(), chunk.start, chunk.end))
+        .collect::<Vec<_>>(),
+      vec![("Tom", 7.0, 10.0), ("the", 27.0, 30.0), ("cat", 42.0, 45.0)]
+    );
+  }
+}
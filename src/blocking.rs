@@ -0,0 +1,47 @@
+use std::sync::{Arc, RwLock};
+
+use napi::tokio::{sync::Semaphore, task};
+use napi::Status;
+use napi_derive::napi;
+use once_cell::sync::Lazy;
+
+use crate::error::Result;
+
+pub const DEFAULT_MAX_CONCURRENCY: usize = 4;
+
+static SEMAPHORE: Lazy<RwLock<Arc<Semaphore>>> =
+  Lazy::new(|| RwLock::new(Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENCY))));
+
+/**
+ * Runs synchronous work on tokio's blocking pool so the threads polling our futures stay free.
+ * At most `max_concurrency` jobs run at once, the rest wait for a permit without blocking
+ */
+pub async fn run<T, F>(work: F) -> napi::Result<T>
+where
+  T: Send + 'static,
+  F: FnOnce() -> Result<T> + Send + 'static,
+{
+  let semaphore = SEMAPHORE.read().unwrap().clone();
+  let _permit = semaphore
+    .acquire_owned()
+    .await
+    .map_err(|err| napi::Error::new(Status::GenericFailure, err.to_string()))?;
+
+  let result = task::spawn_blocking(work)
+    .await
+    .map_err(|err| napi::Error::new(Status::GenericFailure, err.to_string()))?;
+  Ok(result?)
+}
+
+/** Sets how many phonemization or alignment jobs may run at once. Jobs already running keep their permit */
+#[napi]
+pub fn set_max_concurrency(max_concurrency: u32) -> napi::Result<()> {
+  if max_concurrency == 0 {
+    return Err(napi::Error::new(
+      Status::InvalidArg,
+      "max_concurrency must be at least 1",
+    ));
+  }
+  *SEMAPHORE.write().unwrap() = Arc::new(Semaphore::new(max_concurrency as usize));
+  Ok(())
+}
@@ -0,0 +1,136 @@
+use napi_derive::napi;
+use once_cell::sync::Lazy;
+
+/// Punctuation shared by every preset, covering Latin, Greek and Cyrillic script languages
+const COMMON_PUNCTUATION: &str = "!?.,;:()[]{}\"'«»“”‘’„‚‹›—–-…¡¿";
+/// Punctuation which joins the parts of a word, as in "well-known" or "don't"
+const COMMON_JOINERS: &str = "-'’";
+/// Extra punctuation for each language, keyed on the language part of a voice
+const LANGUAGE_PUNCTUATION: [(&[&str], &str); 5] = [
+  (
+    &["zh", "cmn", "yue", "hak", "ja"],
+    "。，、！？：；「」『』（）《》〈〉【】・～",
+  ),
+  (&["ar", "fa", "ur", "ps", "sd"], "،؛؟"),
+  (&["hi", "mr", "ne", "sa", "bn", "pa"], "।॥"),
+  (&["he"], "׃־׳״"),
+  (&["el"], "\u{b7}\u{387}"),
+];
+/// Extra joiners for each language
+const LANGUAGE_JOINERS: [(&[&str], &str); 1] = [(&["he"], "־׳״")];
+
+/// The common preset, used when no profile is given
+pub static DEFAULT_BOUNDARIES: Lazy<BoundaryProfile> = Lazy::new(BoundaryProfile::default);
+/// The punctuation phonemization has always preserved, used when no profile is given so its
+/// output doesn't change. Hyphens inside words are split out too, as there are no joiners
+pub static PRESERVED_PUNCTUATION: Lazy<BoundaryProfile> = Lazy::new(|| BoundaryProfile {
+  punctuation: "!(),-.:;?".to_string(),
+  joiners: String::new(),
+});
+
+/**
+ * Which characters separate words and count as punctuation, shared by text chunking, phoneme
+ * word splitting and punctuation preservation so they all agree
+ */
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoundaryProfile {
+  /// Characters treated as punctuation. They separate words in the text and the phoneme list,
+  /// and are kept when phonemizing with punctuation preserved
+  pub punctuation: String,
+  /// Punctuation which doesn't separate words when it falls between two letters or digits, such
+  /// as the hyphen in "well-known"
+  pub joiners: String,
+}
+
+impl Default for BoundaryProfile {
+  fn default() -> Self {
+    BoundaryProfile {
+      punctuation: COMMON_PUNCTUATION.to_string(),
+      joiners: COMMON_JOINERS.to_string(),
+    }
+  }
+}
+
+impl BoundaryProfile {
+  /**
+   * The preset for a language or voice such as "en", "zh-yue" or espeak's "sit/cmn", the common
+   * one if unknown
+   */
+  pub fn for_language(language: &str) -> Self {
+    let language = language.to_lowercase();
+    // espeak identifiers are prefixed with the language family, such as "gmw/en-US"
+    let language = language.rsplit('/').next().unwrap_or_default();
+    let language = language.split(['-', '_']).next().unwrap_or_default();
+    let extra = |table: &[(&[&str], &str)]| {
+      table
+        .iter()
+        .filter(|(languages, _)| languages.contains(&language))
+        .map(|(_, chars)| *chars)
+        .collect::<String>()
+    };
+
+    let mut profile = BoundaryProfile::default();
+    profile.punctuation.push_str(&extra(&LANGUAGE_PUNCTUATION));
+    profile.joiners.push_str(&extra(&LANGUAGE_JOINERS));
+    profile
+  }
+
+  pub fn is_punctuation(&self, char: char) -> bool {
+    self.punctuation.contains(char)
+  }
+
+  /** Whether the character always separates words, leaving out joiners */
+  pub fn is_separator(&self, char: char) -> bool {
+    char.is_whitespace() || (self.is_punctuation(char) && !self.joiners.contains(char))
+  }
+
+  /** Whether punctuation at this position joins the characters either side of it into a word */
+  pub fn joins(&self, previous: Option<char>, char: char, next: Option<char>) -> bool {
+    self.joiners.contains(char)
+      && previous.is_some_and(char::is_alphanumeric)
+      && next.is_some_and(char::is_alphanumeric)
+  }
+
+  /**
+   * Whether an entry in a phoneme list separates phoneme words. Joiners do, as espeak speaks the
+   * parts of a joined word separately
+   */
+  pub fn is_phoneme_boundary(&self, phoneme: &str) -> bool {
+    !phoneme.is_empty()
+      && phoneme
+        .chars()
+        .all(|char| char.is_whitespace() || self.is_punctuation(char))
+  }
+}
+
+/** The boundary preset for a language or voice, to adjust and pass back in the options */
+#[napi(js_name = "boundaryProfile")]
+pub fn boundary_profile_js(language: String) -> BoundaryProfile {
+  BoundaryProfile::for_language(&language)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::BoundaryProfile;
+
+  #[test]
+  fn test_for_language() {
+    let english = BoundaryProfile::for_language("en-us");
+    assert!(english.is_separator('!') && english.is_separator('»'));
+    assert!(!english.is_separator('-') && english.is_phoneme_boundary("-"));
+    assert!(english.joins(Some('l'), '-', Some('k')));
+    assert!(!english.joins(Some(' '), '-', Some(' ')));
+    assert!(!english.is_separator('。'));
+
+    let chinese = BoundaryProfile::for_language("cmn");
+    assert!(chinese.is_separator('。') && chinese.is_phoneme_boundary("，"));
+    assert!(!chinese.is_phoneme_boundary(""));
+
+    assert_eq!(BoundaryProfile::for_language("sit/cmn"), chinese);
+    assert_eq!(BoundaryProfile::for_language("gmw/en-US"), english);
+    assert!(BoundaryProfile::for_language("sem/he")
+      .joiners
+      .contains('׳'));
+  }
+}
@@ -0,0 +1,177 @@
+use std::num::NonZeroUsize;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use lru::LruCache;
+use napi_derive::napi;
+use once_cell::sync::Lazy;
+
+use crate::phonetics::notation::{PhonemeAlphabet, PhonemeTie};
+use crate::PhonemizeOptions;
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct CacheKey {
+  text: String,
+  /// Identifier of the voice espeak had loaded
+  voice: String,
+  ssml: bool,
+  alphabet: PhonemeAlphabet,
+  tie: PhonemeTie,
+  normalize: bool,
+}
+
+impl CacheKey {
+  pub fn new(text: &str, voice: &str, options: &PhonemizeOptions) -> CacheKey {
+    CacheKey {
+      text: text.to_string(),
+      voice: voice.to_string(),
+      ssml: options.ssml.unwrap_or(false),
+      alphabet: options.alphabet(),
+      tie: options.tie(),
+      normalize: options.normalize.unwrap_or(false),
+    }
+  }
+}
+
+#[napi(object)]
+pub struct PhonemeCacheStats {
+  pub hits: i64,
+  pub misses: i64,
+  /// Number of cached results
+  pub size: u32,
+  /// Maximum number of cached results, 0 when caching is disabled
+  pub capacity: u32,
+}
+
+/** Phonemization results shared across calls, disabled until given a capacity */
+#[derive(Default)]
+struct PhonemeCache {
+  entries: Option<LruCache<CacheKey, String>>,
+  hits: u64,
+  misses: u64,
+}
+
+static CACHE: Lazy<Mutex<PhonemeCache>> = Lazy::new(|| Mutex::new(PhonemeCache::default()));
+
+fn cache() -> MutexGuard<'static, PhonemeCache> {
+  // Every update leaves the cache consistent, so a panic while it was held doesn't matter
+  CACHE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/** The cached phonemes for the key. Lookups aren't counted while caching is disabled */
+pub fn get(key: &CacheKey) -> Option<String> {
+  let mut cache = cache();
+  let phonemes = cache.entries.as_mut()?.get(key).cloned();
+  match phonemes {
+    Some(_) => cache.hits += 1,
+    None => cache.misses += 1,
+  }
+  phonemes
+}
+
+pub fn insert(key: CacheKey, phonemes: String) {
+  if let Some(entries) = cache().entries.as_mut() {
+    entries.put(key, phonemes);
+  }
+}
+
+/**
+ * Sets how many results are cached, evicting the least recently used ones if it shrinks.
+ * A capacity of 0 disables and empties the cache
+ */
+pub fn set_capacity(capacity: usize) {
+  let mut cache = cache();
+  match NonZeroUsize::new(capacity) {
+    Some(capacity) => match cache.entries.as_mut() {
+      Some(entries) => entries.resize(capacity),
+      None => cache.entries = Some(LruCache::new(capacity)),
+    },
+    None => cache.entries = None,
+  }
+}
+
+/** Drops cached results which may be stale, such as after the lexicon changes */
+pub fn invalidate() {
+  if let Some(entries) = cache().entries.as_mut() {
+    entries.clear();
+  }
+}
+
+/** Drops every cached result and resets the hit and miss counts */
+pub fn clear() {
+  let mut cache = cache();
+  if let Some(entries) = cache.entries.as_mut() {
+    entries.clear();
+  }
+  cache.hits = 0;
+  cache.misses = 0;
+}
+
+pub fn stats() -> PhonemeCacheStats {
+  let cache = cache();
+  PhonemeCacheStats {
+    hits: cache.hits as i64,
+    misses: cache.misses as i64,
+    size: cache
+      .entries
+      .as_ref()
+      .map(|entries| entries.len() as u32)
+      .unwrap_or(0),
+    capacity: cache
+      .entries
+      .as_ref()
+      .map(|entries| entries.cap().get() as u32)
+      .unwrap_or(0),
+  }
+}
+
+/**
+ * Caches up to `capacity` phonemization results keyed on their text, voice and options.
+ * Caching is off by default, and setting the capacity to 0 turns it off again
+ */
+#[napi(js_name = "setPhonemeCacheCapacity")]
+pub fn set_phoneme_cache_capacity(capacity: u32) {
+  set_capacity(capacity as usize);
+}
+
+#[napi(js_name = "getPhonemeCacheStats")]
+pub fn get_phoneme_cache_stats() -> PhonemeCacheStats {
+  stats()
+}
+
+#[napi(js_name = "clearPhonemeCache")]
+pub fn clear_phoneme_cache() {
+  clear();
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{clear, get, insert, set_capacity, stats, CacheKey};
+  use crate::PhonemizeOptions;
+
+  #[test]
+  fn test_cache() {
+    let options = PhonemizeOptions::default();
+    let key = |text: &str| CacheKey::new(text, "gmw/en-US", &options);
+
+    // Disabled caches don't store or count anything
+    insert(key("hello"), "həlˈoʊ".to_string());
+    assert_eq!(get(&key("hello")), None);
+    assert_eq!(stats().misses, 0);
+
+    set_capacity(1);
+    insert(key("hello"), "həlˈoʊ".to_string());
+    assert_eq!(get(&key("hello")), Some("həlˈoʊ".to_string()));
+    insert(key("world"), "wˈɜːld".to_string());
+    assert_eq!(get(&key("hello")), None);
+
+    let stats = stats();
+    assert_eq!(
+      (stats.hits, stats.misses, stats.size, stats.capacity),
+      (1, 1, 1, 1)
+    );
+
+    clear();
+    set_capacity(0);
+    assert_eq!(super::stats().capacity, 0);
+  }
+}
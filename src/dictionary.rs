@@ -0,0 +1,240 @@
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::io;
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use espeakng::bindings;
+use napi_derive::napi;
+
+use crate::blocking;
+use crate::error::{Error, Result};
+use crate::speaker::{self, Session};
+use crate::synth::{read_file, status_to_error};
+
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct DictionarySource {
+  /// Contents of the language's `_rules` file
+  pub rules: String,
+  /// Contents of the language's `_list` file
+  pub list: String,
+  /// Contents of the optional `_listx` file, read after `_list`
+  pub listx: Option<String>,
+  /// Contents of the optional `_extra` file
+  pub extra: Option<String>,
+  /// Contents of the optional `_emoji` file
+  pub emoji: Option<String>,
+}
+
+/// Counts the dictionaries compiled by this process, so each gets its own directory
+static COMPILED: AtomicUsize = AtomicUsize::new(0);
+
+/** espeak accepts either an espeak-ng-data directory or the directory containing one */
+fn is_data_path(path: &Path) -> bool {
+  path.join("phontab").is_file() || path.join("espeak-ng-data").join("phontab").is_file()
+}
+
+/** The espeak-ng-data directory espeak is currently loading from */
+fn current_data_path(session: &Session) -> Result<PathBuf> {
+  if let Some(path) = session.data_path() {
+    return Ok(path.to_path_buf());
+  }
+
+  let mut path: *const c_char = std::ptr::null();
+  unsafe { bindings::espeak_Info(&mut path) };
+  if path.is_null() {
+    return Err(Error::Dictionary(
+      "espeak did not report its data path".to_string(),
+    ));
+  }
+  Ok(PathBuf::from(
+    unsafe { CStr::from_ptr(path) }
+      .to_string_lossy()
+      .to_string(),
+  ))
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+  fs::create_dir_all(to)?;
+  for entry in fs::read_dir(from)? {
+    let entry = entry?;
+    let target = to.join(entry.file_name());
+    if entry.file_type()?.is_dir() {
+      copy_dir(&entry.path(), &target)?;
+    } else {
+      fs::copy(entry.path(), target)?;
+    }
+  }
+  Ok(())
+}
+
+/** Copies espeak's data into the root's espeak-ng-data directory, next to the sources */
+fn prepare_data_path(
+  session: &Session,
+  root: &Path,
+  language: &str,
+  source: &DictionarySource,
+) -> Result<()> {
+  let io_error =
+    |err: io::Error| Error::Dictionary(format!("failed to prepare {}: {}", root.display(), err));
+
+  let data_path = root.join("espeak-ng-data");
+  let mut current = current_data_path(session)?;
+  if !current.join("phontab").is_file() {
+    current = current.join("espeak-ng-data");
+  }
+  copy_dir(&current, &data_path).map_err(io_error)?;
+
+  let source_path = root.join("dictsource");
+  fs::create_dir_all(&source_path).map_err(io_error)?;
+  let files = [
+    ("rules", Some(&source.rules)),
+    ("list", Some(&source.list)),
+    ("listx", source.listx.as_ref()),
+    ("extra", source.extra.as_ref()),
+    ("emoji", source.emoji.as_ref()),
+  ];
+  for (suffix, contents) in files {
+    if let Some(contents) = contents {
+      fs::write(
+        source_path.join(format!("{}_{}", language, suffix)),
+        contents,
+      )
+      .map_err(io_error)?;
+    }
+  }
+
+  Ok(())
+}
+
+/** Compiles the dictionary into espeak's current data directory */
+fn compile(source_path: &Path, language: &str) -> Result<()> {
+  // espeak appends file names directly to the source path
+  let source_path = CString::new(format!("{}/", source_path.to_string_lossy()))
+    .map_err(|_| Error::InvalidOption("the source path must not contain NUL".to_string()))?;
+  let language = CString::new(language).unwrap();
+
+  let log = unsafe { bindings::tmpfile() };
+  let mut context: bindings::espeak_ng_ERROR_CONTEXT = std::ptr::null_mut();
+  let status = unsafe {
+    bindings::espeak_ng_CompileDictionary(
+      source_path.as_ptr(),
+      language.as_ptr(),
+      log,
+      0,
+      &mut context,
+    )
+  };
+  unsafe { bindings::espeak_ng_ClearErrorContext(&mut context) };
+
+  let output = if log.is_null() {
+    String::new()
+  } else {
+    let output = unsafe { read_file(log) };
+    unsafe { bindings::fclose(log) };
+    output
+  };
+
+  if status != 0 {
+    return Err(Error::Dictionary(format!(
+      "{}{}",
+      status_to_error(status),
+      if output.trim().is_empty() {
+        String::new()
+      } else {
+        format!("\n{}", output.trim())
+      }
+    )));
+  }
+  Ok(())
+}
+
+/**
+ * Points espeak at another espeak-ng-data directory, or back to its default. Voices loaded
+ * afterwards, and the dictionaries they use, come from that directory
+ */
+pub fn set_data_path(path: Option<&Path>) -> Result<()> {
+  if let Some(path) = path {
+    if !is_data_path(path) {
+      return Err(Error::InvalidOption(format!(
+        "{} is not an espeak-ng-data directory",
+        path.display()
+      )));
+    }
+  }
+
+  let path = path.map(Path::to_path_buf);
+  speaker::run(move |session| session.set_data_path(path, false))
+}
+
+/**
+ * Compiles dictionary sources for the language into a copy of espeak's data directory, which
+ * espeak then loads from. Returns the directory holding the copy, which is deleted when the
+ * data path changes again
+ */
+pub fn compile_dictionary(language: &str, source: &DictionarySource) -> Result<PathBuf> {
+  let is_valid_language = !language.is_empty()
+    && language
+      .chars()
+      .all(|char| char.is_ascii_alphanumeric() || char == '-' || char == '_');
+  if !is_valid_language {
+    return Err(Error::InvalidOption(format!(
+      "\"{}\" is not a valid dictionary name",
+      language
+    )));
+  }
+
+  let language = language.to_string();
+  let source = source.clone();
+  speaker::run(move |session| {
+    let root = std::env::temp_dir().join(format!(
+      "espeak-rs-{}-{}",
+      std::process::id(),
+      COMPILED.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let previous = session.data_path().map(Path::to_path_buf);
+    // Detached so switching to the copy doesn't delete it before we know compiling worked
+    let previous_temporary = session.take_temporary_data_path();
+
+    let result = prepare_data_path(session, &root, &language, &source)
+      .and_then(|_| session.set_data_path(Some(root.clone()), true))
+      .and_then(|_| compile(&root.join("dictsource"), &language));
+
+    match result {
+      Ok(()) => {
+        if let Some(previous) = previous_temporary {
+          let _ = fs::remove_dir_all(previous);
+        }
+        Ok(root)
+      }
+      Err(err) => {
+        // Return to the previous data, which also removes the failed copy
+        session.set_data_path(previous, previous_temporary.is_some())?;
+        let _ = fs::remove_dir_all(&root);
+        Err(err)
+      }
+    }
+  })
+}
+
+#[napi(js_name = "setDataPath")]
+pub async fn set_data_path_async(path: Option<String>) -> napi::Result<()> {
+  blocking::run(move || set_data_path(path.as_deref().map(Path::new))).await
+}
+
+/**
+ * Compiles `_rules` and `_list` sources for the language into a temporary copy of espeak's data,
+ * so phonemization uses them without reinstalling espeak-ng. Returns the directory espeak now
+ * loads from
+ */
+#[napi(js_name = "compileDictionary")]
+pub async fn compile_dictionary_async(
+  language: String,
+  source: DictionarySource,
+) -> napi::Result<String> {
+  let path = blocking::run(move || compile_dictionary(&language, &source)).await?;
+  Ok(path.to_string_lossy().to_string())
+}
@@ -0,0 +1,170 @@
+use napi_derive::napi;
+
+use crate::segment::{segment_sentences, SegmentOptions, SentenceSpan};
+use crate::Chunk;
+
+#[napi(object)]
+#[derive(Clone)]
+pub struct SentenceChunk {
+  pub value: String,
+  pub start: f64,
+  pub end: f64,
+  pub start_time: f64,
+  pub end_time: f64,
+  /// The sentence's words
+  pub chunks: Vec<Chunk>,
+}
+
+#[napi(object)]
+#[derive(Clone)]
+pub struct ParagraphChunk {
+  pub value: String,
+  pub start: f64,
+  pub end: f64,
+  pub start_time: f64,
+  pub end_time: f64,
+  pub sentences: Vec<SentenceChunk>,
+}
+
+/** Times spanning the chunks, or an instant at `fallback` if there are none */
+fn time_range(times: impl Iterator<Item = (f64, f64)>, fallback: f64) -> (f64, f64) {
+  times
+    .fold(
+      None,
+      |range: Option<(f64, f64)>, (start, end)| match range {
+        Some((first, _)) => Some((first, end)),
+        None => Some((start, end)),
+      },
+    )
+    .unwrap_or((fallback, fallback))
+}
+
+/**
+ * Groups aligned words into the paragraphs and sentences of the text they were aligned against.
+ * Sentences come from `segment_sentences`, and a blank line between two sentences starts a new
+ * paragraph. `segmented` is the text to segment, such as the text with markup blanked out, and
+ * must have the same char offsets as `text`
+ */
+pub fn group_chunks(text: &str, segmented: &str, chunks: &[Chunk]) -> Vec<ParagraphChunk> {
+  let chars = text.chars().collect::<Vec<_>>();
+  let slice = |start: usize, end: usize| {
+    chars[start.min(chars.len())..end.min(chars.len())]
+      .iter()
+      .collect::<String>()
+  };
+
+  let spans = segment_sentences(segmented, &SegmentOptions::default());
+  let mut words = chunks.iter().peekable();
+  let mut paragraphs: Vec<ParagraphChunk> = Vec::new();
+  let mut previous: Option<SentenceSpan> = None;
+  let mut time = chunks.first().map(|chunk| chunk.start_time).unwrap_or(0.0);
+
+  for (index, span) in spans.iter().enumerate() {
+    // Words are taken up to the next sentence, so none are lost between sentences
+    let next_start = spans.get(index + 1).map(|next| next.start as f64);
+    let mut sentence_words = Vec::new();
+    while let Some(word) =
+      words.next_if(|word| next_start.map(|next| word.start < next).unwrap_or(true))
+    {
+      sentence_words.push(word.clone());
+    }
+
+    let (start_time, end_time) = time_range(
+      sentence_words
+        .iter()
+        .map(|word| (word.start_time, word.end_time)),
+      time,
+    );
+    time = end_time;
+    let sentence = SentenceChunk {
+      value: slice(span.start, span.end),
+      start: span.start as f64,
+      end: span.end as f64,
+      start_time,
+      end_time,
+      chunks: sentence_words,
+    };
+
+    let is_new_paragraph = match previous {
+      Some(previous) => {
+        segmented[previous.byte_end..span.byte_start]
+          .matches('\n')
+          .count()
+          >= 2
+      }
+      None => true,
+    };
+    match paragraphs.last_mut() {
+      Some(paragraph) if !is_new_paragraph => paragraph.sentences.push(sentence),
+      _ => paragraphs.push(ParagraphChunk {
+        value: String::new(),
+        start: sentence.start,
+        end: sentence.end,
+        start_time: sentence.start_time,
+        end_time: sentence.end_time,
+        sentences: vec![sentence],
+      }),
+    }
+    previous = Some(*span);
+  }
+
+  for paragraph in &mut paragraphs {
+    let last = paragraph.sentences.last().unwrap();
+    paragraph.end = last.end;
+    paragraph.end_time = last.end_time;
+    paragraph.value = slice(paragraph.start as usize, paragraph.end as usize);
+  }
+  paragraphs
+}
+
+#[cfg(test)]
+mod tests {
+  use super::group_chunks;
+  use crate::Chunk;
+
+  #[test]
+  fn test_group_chunks() {
+    let text = "Hi there. Bye!\n\nNew one.";
+    let chunks = [(0, 2), (3, 8), (10, 13), (16, 19), (20, 23)]
+      .into_iter()
+      .enumerate()
+      .map(|(index, (start, end))| Chunk {
+        value: text[start..end].to_string(),
+        start: start as f64,
+        end: end as f64,
+        start_time: index as f64 * 100.0,
+        end_time: (index + 1) as f64 * 100.0,
+        ..Default::default()
+      })
+      .collect::<Vec<_>>();
+
+    let paragraphs = group_chunks(text, text, &chunks);
+    let summary = paragraphs
+      .iter()
+      .map(|paragraph| {
+        (
+          paragraph.value.as_str(),
+          paragraph.start_time,
+          paragraph.end_time,
+          paragraph
+            .sentences
+            .iter()
+            .map(|sentence| (sentence.value.as_str(), sentence.chunks.len()))
+            .collect::<Vec<_>>(),
+        )
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(
+      summary,
+      vec![
+        (
+          "Hi there. Bye!",
+          0.0,
+          300.0,
+          vec![("Hi there.", 2), ("Bye!", 1)]
+        ),
+        ("New one.", 300.0, 500.0, vec![("New one.", 2)]),
+      ]
+    );
+  }
+}
@@ -0,0 +1,94 @@
+use std::fmt;
+
+use napi::Status;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+  /// espeak-ng could not be initialised
+  EspeakInit(espeakng::Error),
+  /// The requested voice or language isn't installed with espeak-ng
+  VoiceNotFound(String),
+  /// espeak-ng failed while converting text to phonemes
+  Phonemization(espeakng::Error),
+  /// espeak-ng failed while synthesizing speech
+  Synthesis(espeakng::Error),
+  /// The number of end times doesn't match the number of phonemes they belong to
+  LengthMismatch { end_times: usize, phonemes: usize },
+  /// The given text or phonemes were empty
+  EmptyInput(&'static str),
+  /// An option was outside of its accepted range
+  InvalidOption(String),
+  /// A lexicon file or entry couldn't be read
+  Lexicon(String),
+  /// A custom dictionary couldn't be compiled
+  Dictionary(String),
+  /// The speaker thread stopped or a job panicked before replying
+  SpeakerUnavailable,
+}
+
+impl Error {
+  /** Stable identifier for the error, surfaced to JS as the prefix of the message */
+  pub fn code(&self) -> &'static str {
+    match self {
+      Self::EspeakInit(_) => "ESPEAK_INIT_FAILED",
+      Self::VoiceNotFound(_) => "VOICE_NOT_FOUND",
+      Self::Phonemization(_) => "PHONEMIZATION_FAILED",
+      Self::Synthesis(_) => "SYNTHESIS_FAILED",
+      Self::LengthMismatch { .. } => "LENGTH_MISMATCH",
+      Self::EmptyInput(_) => "EMPTY_INPUT",
+      Self::InvalidOption(_) => "INVALID_OPTION",
+      Self::Lexicon(_) => "INVALID_LEXICON",
+      Self::Dictionary(_) => "DICTIONARY_COMPILE_FAILED",
+      Self::SpeakerUnavailable => "SPEAKER_UNAVAILABLE",
+    }
+  }
+
+  fn status(&self) -> Status {
+    match self {
+      Self::EspeakInit(_)
+      | Self::Phonemization(_)
+      | Self::Synthesis(_)
+      | Self::Dictionary(_)
+      | Self::SpeakerUnavailable => Status::GenericFailure,
+      Self::VoiceNotFound(_)
+      | Self::LengthMismatch { .. }
+      | Self::EmptyInput(_)
+      | Self::InvalidOption(_)
+      | Self::Lexicon(_) => Status::InvalidArg,
+    }
+  }
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::EspeakInit(err) => write!(f, "failed to initialise espeak-ng: {}", err),
+      Self::VoiceNotFound(voice) => write!(f, "voice \"{}\" is not installed", voice),
+      Self::Phonemization(err) => write!(f, "espeak-ng failed to phonemize: {}", err),
+      Self::Synthesis(err) => write!(f, "espeak-ng failed to synthesize: {}", err),
+      Self::LengthMismatch {
+        end_times,
+        phonemes,
+      } => write!(
+        f,
+        "expected one end time per phoneme but got {} end times for {} phonemes",
+        end_times, phonemes
+      ),
+      Self::EmptyInput(name) => write!(f, "{} must not be empty", name),
+      Self::InvalidOption(message) => write!(f, "{}", message),
+      Self::Lexicon(message) => write!(f, "{}", message),
+      Self::Dictionary(message) => write!(f, "failed to compile dictionary: {}", message),
+      Self::SpeakerUnavailable => write!(f, "the espeak speaker thread is unavailable"),
+    }
+  }
+}
+
+impl From<Error> for napi::Error {
+  fn from(err: Error) -> Self {
+    napi::Error::new(err.status(), format!("{}: {}", err.code(), err))
+  }
+}
@@ -0,0 +1,380 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard};
+
+use napi_derive::napi;
+use once_cell::sync::Lazy;
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+
+use crate::cache;
+use crate::error::{Error, Result};
+
+#[napi(object)]
+#[derive(Clone, Debug, Deserialize)]
+pub struct LexiconEntry {
+  /// The word to override, matched case-insensitively, or a pattern if `regex` is set
+  pub word: String,
+  /// The pronunciation to use instead of espeak's, in IPA
+  pub phonemes: String,
+  /// Only overrides voices of this language, such as "en" or "en-gb"
+  pub language: Option<String>,
+  /// Treats `word` as a regular expression
+  pub regex: Option<bool>,
+}
+
+/** A lexicon file is either a list of entries or an object of words to phonemes */
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LexiconFile {
+  Entries(Vec<LexiconEntry>),
+  Words(BTreeMap<String, String>),
+}
+
+struct Override {
+  entry: LexiconEntry,
+  pattern: Regex,
+  /// The phonemes with spaces removed, so the override is always a single word
+  phonemes: String,
+  /// The phonemes without stress, for finding the entry spoken as a phoneme word
+  key: String,
+}
+
+impl Override {
+  fn new(entry: LexiconEntry) -> Result<Override> {
+    let is_regex = entry.regex.unwrap_or(false);
+    let pattern = if is_regex {
+      entry.word.clone()
+    } else {
+      regex::escape(entry.word.trim())
+    };
+    let pattern = RegexBuilder::new(&pattern)
+      .case_insensitive(!is_regex)
+      .build()
+      .map_err(|err| Error::Lexicon(format!("invalid pattern \"{}\": {}", entry.word, err)))?;
+
+    let phonemes = entry
+      .phonemes
+      .chars()
+      .filter(|char| !char.is_whitespace())
+      .collect::<String>();
+    if phonemes.is_empty() {
+      return Err(Error::Lexicon(format!(
+        "\"{}\" has no phonemes",
+        entry.word
+      )));
+    }
+
+    Ok(Override {
+      key: phoneme_key(&phonemes),
+      entry,
+      pattern,
+      phonemes,
+    })
+  }
+
+  fn is_regex(&self) -> bool {
+    self.entry.regex.unwrap_or(false)
+  }
+
+  /** Whether the override applies to a voice identifier such as "gmw/en-US" */
+  fn applies_to(&self, voice: &str) -> bool {
+    let language = match &self.entry.language {
+      Some(language) => language.to_lowercase(),
+      None => return true,
+    };
+    let voice = voice.rsplit('/').next().unwrap_or(voice).to_lowercase();
+    voice == language || voice.starts_with(&format!("{}-", language))
+  }
+
+  /** Matches of the override in the text, as byte ranges. Words only match whole words */
+  fn find_in(&self, text: &str) -> Vec<(usize, usize)> {
+    let is_word_char = |char: Option<char>| char.map(char::is_alphanumeric).unwrap_or(false);
+    self
+      .pattern
+      .find_iter(text)
+      .filter(|found| !found.as_str().is_empty())
+      .filter(|found| {
+        self.is_regex()
+          || (!is_word_char(text[..found.start()].chars().next_back())
+            && !is_word_char(text[found.end()..].chars().next()))
+      })
+      .map(|found| (found.start(), found.end()))
+      .collect()
+  }
+}
+
+/** Strips stress marks and spaces, so phonemes can be compared however they were written */
+fn phoneme_key(phonemes: &str) -> String {
+  phonemes
+    .chars()
+    .filter(|char| !char.is_whitespace() && !matches!(char, 'ˈ' | 'ˌ'))
+    .collect()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LexiconMatch {
+  /// Byte range of the overridden text
+  pub start: usize,
+  pub end: usize,
+  /// The override's phonemes in IPA, without spaces
+  pub phonemes: String,
+}
+
+/** Pronunciations which replace espeak's for matching words */
+#[derive(Default)]
+pub struct Lexicon {
+  overrides: Vec<Override>,
+}
+
+impl Lexicon {
+  /** Adds the entries, leaving the lexicon unchanged if any of them are invalid */
+  pub fn add(&mut self, entries: Vec<LexiconEntry>) -> Result<()> {
+    let overrides = entries
+      .into_iter()
+      .map(Override::new)
+      .collect::<Result<Vec<_>>>()?;
+    self.overrides.extend(overrides);
+    Ok(())
+  }
+
+  pub fn clear(&mut self) {
+    self.overrides.clear();
+  }
+
+  pub fn len(&self) -> usize {
+    self.overrides.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.overrides.is_empty()
+  }
+
+  /**
+   * Finds the overrides for the voice in the text, ordered and without overlaps. Earlier
+   * matches win, then longer ones, then the entry added first
+   */
+  pub fn find(&self, text: &str, voice: &str) -> Vec<LexiconMatch> {
+    let mut candidates = self
+      .overrides
+      .iter()
+      .filter(|entry| entry.applies_to(voice))
+      .flat_map(|entry| {
+        entry
+          .find_in(text)
+          .into_iter()
+          .map(move |(start, end)| (start, end, entry))
+      })
+      .collect::<Vec<_>>();
+    // Stable, so equal matches keep the order entries were added in
+    candidates.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+
+    let mut matches: Vec<LexiconMatch> = Vec::new();
+    for (start, end, entry) in candidates {
+      if matches.last().map(|last| start < last.end).unwrap_or(false) {
+        continue;
+      }
+      matches.push(LexiconMatch {
+        start,
+        end,
+        phonemes: entry.phonemes.clone(),
+      });
+    }
+    matches
+  }
+
+  /** Whether an override in any language covers the whole word */
+  pub fn covers(&self, word: &str) -> bool {
+    self
+      .overrides
+      .iter()
+      .any(|entry| entry.find_in(word).contains(&(0, word.len())))
+  }
+
+  /** The entry for the voice whose override has these phonemes, ignoring stress and spaces */
+  pub fn entry_for_phonemes(&self, phonemes: &str, voice: &str) -> Option<&LexiconEntry> {
+    let key = phoneme_key(phonemes);
+    self
+      .overrides
+      .iter()
+      .find(|entry| entry.key == key && entry.applies_to(voice))
+      .map(|entry| &entry.entry)
+  }
+
+  pub fn parse_json(source: &str) -> Result<Vec<LexiconEntry>> {
+    let file: LexiconFile = serde_json::from_str(source)
+      .map_err(|err| Error::Lexicon(format!("invalid JSON lexicon: {}", err)))?;
+    Ok(match file {
+      LexiconFile::Entries(entries) => entries,
+      LexiconFile::Words(words) => words
+        .into_iter()
+        .map(|(word, phonemes)| LexiconEntry {
+          word,
+          phonemes,
+          language: None,
+          regex: None,
+        })
+        .collect(),
+    })
+  }
+
+  /**
+   * Parses lines of `word<TAB>phonemes`, optionally followed by a language. Words wrapped in
+   * slashes are regular expressions, and empty lines or lines starting with # are skipped
+   */
+  pub fn parse_tsv(source: &str) -> Result<Vec<LexiconEntry>> {
+    source
+      .lines()
+      .enumerate()
+      .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+      .map(|(index, line)| {
+        let columns = line.split('\t').map(str::trim).collect::<Vec<_>>();
+        if columns.len() < 2 || columns.len() > 3 {
+          return Err(Error::Lexicon(format!(
+            "line {} should have 2 or 3 tab separated columns but has {}",
+            index + 1,
+            columns.len()
+          )));
+        }
+
+        let word = columns[0];
+        let regex = word.len() > 1 && word.starts_with('/') && word.ends_with('/');
+        Ok(LexiconEntry {
+          word: if regex {
+            word[1..word.len() - 1].to_string()
+          } else {
+            word.to_string()
+          },
+          phonemes: columns[1].to_string(),
+          language: columns
+            .get(2)
+            .filter(|language| !language.is_empty())
+            .map(|language| language.to_string()),
+          regex: Some(regex),
+        })
+      })
+      .collect()
+  }
+}
+
+static LEXICON: Lazy<RwLock<Lexicon>> = Lazy::new(|| RwLock::new(Lexicon::default()));
+
+/** The global lexicon used by phonemization and alignment */
+pub fn lexicon() -> RwLockReadGuard<'static, Lexicon> {
+  // The lexicon is never left half updated, so a panic while it was held doesn't matter
+  LEXICON.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/** Adds entries to the global lexicon, dropping cached phonemes they may have changed */
+pub fn add_entries(entries: Vec<LexiconEntry>) -> Result<()> {
+  LEXICON
+    .write()
+    .unwrap_or_else(PoisonError::into_inner)
+    .add(entries)?;
+  cache::invalidate();
+  Ok(())
+}
+
+/** Loads a `.json` lexicon, or a tab separated one for any other extension */
+pub fn load_file(path: &Path) -> Result<usize> {
+  let source = std::fs::read_to_string(path)
+    .map_err(|err| Error::Lexicon(format!("failed to read {}: {}", path.display(), err)))?;
+  let entries = match path.extension().and_then(|extension| extension.to_str()) {
+    Some(extension) if extension.eq_ignore_ascii_case("json") => Lexicon::parse_json(&source)?,
+    _ => Lexicon::parse_tsv(&source)?,
+  };
+  let count = entries.len();
+  add_entries(entries)?;
+  Ok(count)
+}
+
+pub fn clear() {
+  LEXICON
+    .write()
+    .unwrap_or_else(PoisonError::into_inner)
+    .clear();
+  cache::invalidate();
+}
+
+/** Adds pronunciation overrides, which take precedence over espeak for matching words */
+#[napi(js_name = "addLexiconEntries")]
+pub fn add_lexicon_entries(entries: Vec<LexiconEntry>) -> napi::Result<()> {
+  Ok(add_entries(entries)?)
+}
+
+/**
+ * Adds the overrides from a lexicon file. `.json` files hold a list of entries or an object of
+ * words to phonemes, anything else is read as tab separated `word, phonemes, language` lines.
+ * Returns the number of entries added
+ */
+#[napi(js_name = "loadLexicon")]
+pub fn load_lexicon(path: String) -> napi::Result<u32> {
+  Ok(load_file(Path::new(&path))? as u32)
+}
+
+#[napi(js_name = "clearLexicon")]
+pub fn clear_lexicon() {
+  clear();
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Lexicon, LexiconMatch};
+
+  #[test]
+  fn test_find() {
+    let mut lexicon = Lexicon::default();
+    lexicon
+      .add(
+        Lexicon::parse_tsv(
+          "# brand names\nNginx\tˈɛndʒɪn ˈɛks\n/iPhone ?\\d+/\tˈaɪfoʊn\ncolour\tˈkʌlə\ten-gb\n",
+        )
+        .unwrap(),
+      )
+      .unwrap();
+
+    let text = "nginx on my iPhone 15, in colour. Nginxes";
+    assert_eq!(
+      lexicon.find(text, "gmw/en-US"),
+      vec![
+        LexiconMatch {
+          start: 0,
+          end: 5,
+          phonemes: "ˈɛndʒɪnˈɛks".to_string()
+        },
+        LexiconMatch {
+          start: 12,
+          end: 21,
+          phonemes: "ˈaɪfoʊn".to_string()
+        },
+      ]
+    );
+    assert_eq!(lexicon.find(text, "gmw/en-GB").len(), 3);
+
+    assert!(lexicon.covers("iPhone15"));
+    assert!(!lexicon.covers("Nginxes"));
+    assert_eq!(
+      lexicon
+        .entry_for_phonemes("ɛndʒɪnɛks", "gmw/en-US")
+        .map(|entry| entry.word.as_str()),
+      Some("Nginx")
+    );
+    assert!(lexicon.entry_for_phonemes("kʌlə", "gmw/en-US").is_none());
+    assert!(lexicon.entry_for_phonemes("kʌlə", "gmw/en-GB").is_some());
+  }
+
+  #[test]
+  fn test_parse_json() {
+    let entries = Lexicon::parse_json(r#"{ "Nginx": "ˈɛndʒɪn ˈɛks" }"#).unwrap();
+    assert_eq!(entries[0].word, "Nginx");
+
+    let entries =
+      Lexicon::parse_json(r#"[{ "word": "colour", "phonemes": "ˈkʌlə", "language": "en-gb" }]"#)
+        .unwrap();
+    assert_eq!(entries[0].language.as_deref(), Some("en-gb"));
+
+    assert!(Lexicon::default()
+      .add(Lexicon::parse_json(r#"[{ "word": "(", "phonemes": "a", "regex": true }]"#).unwrap())
+      .is_err());
+  }
+}
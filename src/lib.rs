@@ -7,8 +7,29 @@ use once_cell::sync::Lazy;
 use regex::Regex;
 
 pub mod align;
+pub mod blocking;
+pub mod boundary;
+pub mod cache;
+pub mod dictionary;
+pub mod document;
+pub mod error;
 mod leven;
+pub mod lexicon;
+pub mod normalize;
+pub mod offsets;
 pub mod phonetics;
+pub mod segment;
+mod sequence;
+pub mod speaker;
+pub mod synth;
+pub mod tokenize;
+pub mod voice;
+
+use crate::align::MatchKind;
+use crate::boundary::BoundaryProfile;
+use crate::document::ParagraphChunk;
+use crate::error::Result;
+use crate::phonetics::notation::{PhonemeAlphabet, PhonemeTie};
 
 pub static COMBINED_PHONEME_MAPPING: Lazy<HashMap<&'static str, [&'static str; 2]>> =
   Lazy::new(|| {
@@ -140,12 +161,6 @@ pub static LETTER_PHONEMES: Lazy<[&'static str; 26]> = Lazy::new(|| {
 });
 pub static PHONETIC_WORD_TAG_BOUNDARIES: Lazy<[&'static str; 4]> =
   Lazy::new(|| ["<w>", "<s>", "</w>", "</s>"]);
-pub static PHONETIC_WORD_BOUNDARY: Lazy<[&'static str; 22]> = Lazy::new(|| {
-  [
-    "!", "(", ")", "-", ";", ":", ",", ".", "?", "¡", "¿", "—", "…", "'", "«", "»", "“", "”", " ",
-    "\n", "</w>", "</s>",
-  ]
-});
 pub static ACRONYM_REGEX: Lazy<Regex> = Lazy::new(|| {
   Regex::new(
     format!(
@@ -175,26 +190,32 @@ pub static ACRONYM_REGEX_SPLIT: Lazy<Regex> = Lazy::new(|| {
   .unwrap()
 });
 
-pub fn text_to_phonemes(text: &str) -> String {
-  let mut speaker = espeakng::initialise(Some("en-us")).unwrap().lock();
-  speaker.set_voice_raw("en-US").unwrap();
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct PhonemizeOptions {
+  /// Parses the text as SSML rather than speaking markup literally
+  pub ssml: Option<bool>,
+  /// The notation phonemes are written in, IPA by default
+  pub alphabet: Option<PhonemeAlphabet>,
+  /// How multi-letter phonemes are joined, not joined by default
+  pub tie: Option<PhonemeTie>,
+  /// Expands numbers, dates, times, currencies, ordinals and units into words before espeak
+  /// reads them. The words are English, so other voices fail with INVALID_OPTION. Ignored for
+  /// SSML
+  pub normalize: Option<bool>,
+  /// Which punctuation is kept when preserving punctuation. Defaults to the original
+  /// `!(),-.:;?`, so language presets from `boundaryProfile` have to be passed in
+  pub boundaries: Option<BoundaryProfile>,
+}
 
-  text
-    .split_inclusive([',', '.', '?', '!'])
-    .map(|text| {
-      speaker
-        .text_to_phonemes(
-          text,
-          espeakng::PhonemeGenOptions::Standard {
-            text_mode: espeakng::TextMode::Utf8,
-            phoneme_mode: espeakng::PhonemeMode::IncludeZeroWidthJoiners,
-          },
-        )
-        .unwrap()
-        .unwrap()
-    })
-    .collect::<Vec<_>>()
-    .join(" ")
+pub fn text_to_phonemes(text: &str, voice: &str, options: &PhonemizeOptions) -> Result<String> {
+  let text = text.to_string();
+  let voice = voice.to_string();
+  let options = options.clone();
+  speaker::run(move |session| {
+    session.set_voice(&voice)?;
+    session.text_to_phonemes(&text, &options)
+  })
 }
 
 #[napi(object)]
@@ -206,16 +227,35 @@ pub struct NestedChunk {
   pub start_time: f64,
   pub end_time: f64,
   pub chunks: Vec<Chunk>,
+  /// Mean confidence of the aligned words, from 0 to 1
+  pub quality: Option<f64>,
+  /// The words grouped into paragraphs and sentences, when `groupSentences` is set and text
+  /// rather than phonemes is aligned
+  pub paragraphs: Option<Vec<ParagraphChunk>>,
 }
 
 #[napi(object)]
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Chunk {
   pub value: String,
   pub start: f64,
   pub end: f64,
   pub start_time: f64,
   pub end_time: f64,
+  /// The phonemes matched to the word, when alignment includes phonemes
+  pub phonemes: Option<String>,
+  /// Range of the word's phonemes in the phoneme list
+  pub phoneme_start: Option<f64>,
+  pub phoneme_end: Option<f64>,
+  /// One chunk per phoneme, whose offsets index the phoneme list
+  pub phoneme_chunks: Option<Vec<Chunk>>,
+  /// How confident the aligner is in the word's match, from 0 to 1
+  pub confidence: Option<f64>,
+  /// How the word was matched to its phonemes
+  pub match_kind: Option<MatchKind>,
+  /// The parts of a hyphenated compound or contraction, each aligned to the phoneme words
+  /// espeak spoke it as
+  pub parts: Option<Vec<Chunk>>,
 }
 
 #[napi(object)]
@@ -232,20 +272,23 @@ pub struct PhonemeChunk {
 pub fn transform_raw_phoneme_timestamps(
   phoneme_list: &Vec<&str>,
   end_times: &Vec<f64>,
+  boundaries: &BoundaryProfile,
+  voice: &str,
 ) -> Vec<PhonemeChunk> {
   let mut words: Vec<PhonemeChunk> = Vec::new();
+  let lexicon = lexicon::lexicon();
 
   let mut i = 0;
   while i < phoneme_list.len() {
     // Loop until we find a char that isn't a phonetic boundary
-    while i < phoneme_list.len() && is_phonetic_word_boundary(phoneme_list.get(i).unwrap()) {
+    while i < phoneme_list.len() && is_phonetic_word_boundary(phoneme_list[i], boundaries) {
       i += 1;
     }
 
     // Gather chars until we reach another boundary or end of list
     let start = i;
     let mut value = String::new();
-    while i < phoneme_list.len() && !is_phonetic_word_boundary(phoneme_list.get(i).unwrap()) {
+    while i < phoneme_list.len() && !is_phonetic_word_boundary(phoneme_list[i], boundaries) {
       let phoneme = phoneme_list.get(i).unwrap();
       if !PHONETIC_WORD_TAG_BOUNDARIES.contains(phoneme) {
         value.push_str(phoneme);
@@ -253,8 +296,13 @@ pub fn transform_raw_phoneme_timestamps(
       i += 1;
     }
 
+    // Lexicon overrides are always a single word, written for a known word
+    let override_entry = lexicon.entry_for_phonemes(value.as_str(), voice);
+
     // Split apart known combined phonemes
-    let values = if COMBINED_PHONEME_MAPPING.contains_key(value.as_str()) {
+    let values = if override_entry.is_some() {
+      Vec::from([value])
+    } else if COMBINED_PHONEME_MAPPING.contains_key(value.as_str()) {
       let values = COMBINED_PHONEME_MAPPING.get(value.as_str()).unwrap();
       values.iter().map(|str| str.to_string()).collect()
     } else {
@@ -270,7 +318,10 @@ pub fn transform_raw_phoneme_timestamps(
       let length = value.chars().count();
       let end = start + length;
 
-      let phoneme_word = phoneme_to_word(value.as_str()).trim().to_string();
+      let phoneme_word = match override_entry {
+        Some(entry) if !entry.regex.unwrap_or(false) => entry.word.clone(),
+        _ => phoneme_to_word(value.as_str()).trim().to_string(),
+      };
 
       words.push(PhonemeChunk {
         value,
@@ -288,8 +339,8 @@ pub fn transform_raw_phoneme_timestamps(
   words
 }
 
-fn is_phonetic_word_boundary(phoneme: &str) -> bool {
-  PHONETIC_WORD_BOUNDARY.contains(&phoneme)
+fn is_phonetic_word_boundary(phoneme: &str, boundaries: &BoundaryProfile) -> bool {
+  boundaries.is_phoneme_boundary(phoneme) || matches!(phoneme, "</w>" | "</s>")
 }
 
 pub fn phoneme_to_word(phoneme: &str) -> String {
@@ -325,4 +376,3 @@ pub fn phoneme_to_word(phoneme: &str) -> String {
   }
   word
 }
-
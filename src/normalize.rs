@@ -0,0 +1,544 @@
+use napi_derive::napi;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+use crate::error::{Error, Result};
+
+const ONES: [&str; 20] = [
+  "zero",
+  "one",
+  "two",
+  "three",
+  "four",
+  "five",
+  "six",
+  "seven",
+  "eight",
+  "nine",
+  "ten",
+  "eleven",
+  "twelve",
+  "thirteen",
+  "fourteen",
+  "fifteen",
+  "sixteen",
+  "seventeen",
+  "eighteen",
+  "nineteen",
+];
+const TENS: [&str; 10] = [
+  "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+const SCALES: [(u64, &str); 5] = [
+  (1_000_000_000_000_000, "quadrillion"),
+  (1_000_000_000_000, "trillion"),
+  (1_000_000_000, "billion"),
+  (1_000_000, "million"),
+  (1_000, "thousand"),
+];
+const MONTHS: [&str; 12] = [
+  "January",
+  "February",
+  "March",
+  "April",
+  "May",
+  "June",
+  "July",
+  "August",
+  "September",
+  "October",
+  "November",
+  "December",
+];
+struct Currency {
+  symbol: &'static str,
+  /// Singular and plural names of the major unit
+  major: (&'static str, &'static str),
+  /// Singular and plural names of the minor unit, if amounts are read with one
+  minor: Option<(&'static str, &'static str)>,
+}
+
+const CURRENCIES: [Currency; 4] = [
+  Currency {
+    symbol: "$",
+    major: ("dollar", "dollars"),
+    minor: Some(("cent", "cents")),
+  },
+  Currency {
+    symbol: "£",
+    major: ("pound", "pounds"),
+    minor: Some(("penny", "pence")),
+  },
+  Currency {
+    symbol: "€",
+    major: ("euro", "euros"),
+    minor: Some(("cent", "cents")),
+  },
+  Currency {
+    symbol: "¥",
+    major: ("yen", "yen"),
+    minor: None,
+  },
+];
+/// Unit symbols with their singular and plural names, longest first so "km/h" beats "km"
+const UNITS: [(&str, &str, &str); 20] = [
+  ("km/h", "kilometer per hour", "kilometers per hour"),
+  ("mph", "mile per hour", "miles per hour"),
+  ("lbs", "pound", "pounds"),
+  ("°C", "degree Celsius", "degrees Celsius"),
+  ("°F", "degree Fahrenheit", "degrees Fahrenheit"),
+  ("km", "kilometer", "kilometers"),
+  ("kg", "kilogram", "kilograms"),
+  ("cm", "centimeter", "centimeters"),
+  ("mm", "millimeter", "millimeters"),
+  ("mg", "milligram", "milligrams"),
+  ("ml", "milliliter", "milliliters"),
+  ("ft", "foot", "feet"),
+  ("lb", "pound", "pounds"),
+  ("oz", "ounce", "ounces"),
+  ("%", "percent", "percent"),
+  ("m", "meter", "meters"),
+  ("g", "gram", "grams"),
+  ("l", "liter", "liters"),
+  ("s", "second", "seconds"),
+  ("h", "hour", "hours"),
+];
+
+const NUMBER: &str = r"\d{1,3}(?:,\d{3})+(?:\.\d+)?|\d+(?:\.\d+)?";
+
+static REGEX_NORMALIZABLE: Lazy<Regex> = Lazy::new(|| {
+  // "%" is matched on its own, as no word boundary follows it
+  let units = |single_letter: bool| {
+    UNITS
+      .iter()
+      .filter(|(symbol, _, _)| *symbol != "%")
+      .filter(|(symbol, _, _)| (symbol.chars().count() == 1) == single_letter)
+      .map(|(symbol, _, _)| regex::escape(symbol))
+      .collect::<Vec<_>>()
+      .join("|")
+  };
+  let currencies = CURRENCIES
+    .iter()
+    .map(|currency| regex::escape(currency.symbol))
+    .collect::<Vec<_>>()
+    .join("|");
+  Regex::new(&format!(
+    concat!(
+      r"\b(?P<iso_year>\d{{4}})-(?P<iso_month>\d{{2}})-(?P<iso_day>\d{{2}})\b",
+      r"|\b(?P<month>\d{{1,2}})/(?P<day>\d{{1,2}})/(?P<year>\d{{4}})\b",
+      r"|\b(?P<hour>\d{{1,2}}):(?P<minute>\d{{2}})(?:\s?(?P<meridiem>[aApP])\.?[mM]\b\.?)?",
+      r"|(?P<currency>{currencies})(?P<amount>{number})\b",
+      r"|\b(?P<ordinal>\d+)(?:st|nd|rd|th)\b",
+      // Single letter units such as "s" would turn up inside words, so they need a space first
+      r"|(?P<sign>-)?\b(?P<number>{number})(?P<unit>\s?%|\s?(?:{units})\b|\s(?:{letters})\b)?",
+    ),
+    currencies = currencies,
+    number = NUMBER,
+    units = units(false),
+    letters = units(true),
+  ))
+  .unwrap()
+});
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct NormalizedSpan {
+  /// Offset of the expansion in the normalized text, in chars
+  pub start: f64,
+  pub end: f64,
+  /// Offset of the token it replaced in the original text, in chars
+  pub original_start: f64,
+  pub original_end: f64,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct NormalizedText {
+  pub text: String,
+  /// Expanded tokens in order. Text between them is unchanged, so maps back one to one
+  pub spans: Vec<NormalizedSpan>,
+}
+
+impl NormalizedText {
+  /** Maps a char offset in the normalized text back to the original text */
+  fn original_position(&self, position: usize, is_end: bool) -> usize {
+    let mut shift = 0;
+    for span in &self.spans {
+      let (start, end) = (span.start as usize, span.end as usize);
+      if position < start || (is_end && position == start) {
+        break;
+      }
+      if position < end || (is_end && position == end) {
+        return if is_end {
+          span.original_end as usize
+        } else {
+          span.original_start as usize
+        };
+      }
+      shift = span.original_end as isize - span.end as isize;
+    }
+    (position as isize + shift) as usize
+  }
+
+  /**
+   * Maps a char range of the normalized text back to the original text. Ranges touching an
+   * expansion widen to the whole token it replaced, so each word of "twenty one" maps to "21"
+   */
+  pub fn original_range(&self, start: usize, end: usize) -> (usize, usize) {
+    (
+      self.original_position(start, false),
+      self.original_position(end, true),
+    )
+  }
+}
+
+fn cardinal_below_thousand(number: u64) -> String {
+  let mut words = Vec::new();
+  if number >= 100 {
+    words.push(format!("{} hundred", ONES[(number / 100) as usize]));
+  }
+  match number % 100 {
+    0 => {}
+    rest @ 1..=19 => words.push(ONES[rest as usize].to_string()),
+    rest => {
+      words.push(TENS[(rest / 10) as usize].to_string());
+      if rest % 10 != 0 {
+        words.push(ONES[(rest % 10) as usize].to_string());
+      }
+    }
+  }
+  words.join(" ")
+}
+
+pub fn cardinal(number: u64) -> String {
+  if number == 0 {
+    return ONES[0].to_string();
+  }
+
+  let mut words = Vec::new();
+  let mut rest = number;
+  for (scale, name) in SCALES {
+    if rest >= scale {
+      words.push(format!("{} {}", cardinal(rest / scale), name));
+      rest %= scale;
+    }
+  }
+  if rest > 0 {
+    words.push(cardinal_below_thousand(rest));
+  }
+  words.join(" ")
+}
+
+pub fn ordinal(number: u64) -> String {
+  let words = cardinal(number);
+  let (head, last) = match words.rsplit_once(' ') {
+    Some((head, last)) => (format!("{} ", head), last),
+    None => (String::new(), words.as_str()),
+  };
+  let last = match last {
+    "one" => "first".to_string(),
+    "two" => "second".to_string(),
+    "three" => "third".to_string(),
+    "five" => "fifth".to_string(),
+    "eight" => "eighth".to_string(),
+    "nine" => "ninth".to_string(),
+    "twelve" => "twelfth".to_string(),
+    last if last.ends_with('y') => format!("{}ieth", &last[..last.len() - 1]),
+    last => format!("{}th", last),
+  };
+  head + &last
+}
+
+/** Reads a year the way it's spoken, as in "nineteen ninety nine" or "two thousand five" */
+pub fn year(number: u64) -> String {
+  let (century, rest) = (number / 100, number % 100);
+  if !(1100..10000).contains(&number) || (number % 1000 < 10 && century % 10 == 0) {
+    return cardinal(number);
+  }
+  match rest {
+    0 => format!("{} hundred", cardinal(century)),
+    1..=9 => format!("{} oh {}", cardinal(century), cardinal(rest)),
+    _ => format!("{} {}", cardinal(century), cardinal(rest)),
+  }
+}
+
+/** Reads a number with optional thousands separators and decimals, as in "3.14" */
+fn number(digits: &str) -> String {
+  let digits = digits.replace(',', "");
+  let (whole, fraction) = match digits.split_once('.') {
+    Some((whole, fraction)) => (whole, Some(fraction)),
+    None => (digits.as_str(), None),
+  };
+
+  // Numbers too long to read as a whole are read digit by digit
+  let mut words = match whole.parse::<u64>() {
+    Ok(whole) if whole < 1_000_000_000_000_000_000 => cardinal(whole),
+    _ => spell_digits(whole),
+  };
+  if let Some(fraction) = fraction {
+    words.push_str(" point ");
+    words.push_str(&spell_digits(fraction));
+  }
+  words
+}
+
+fn spell_digits(digits: &str) -> String {
+  digits
+    .chars()
+    .filter_map(|digit| digit.to_digit(10))
+    .map(|digit| ONES[digit as usize])
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+fn date(year_digits: &str, month: &str, day: &str) -> Option<String> {
+  let month = month
+    .parse::<usize>()
+    .ok()
+    .filter(|month| (1..=12).contains(month))?;
+  let day = day
+    .parse::<u64>()
+    .ok()
+    .filter(|day| (1..=31).contains(day))?;
+  Some(format!(
+    "{} {} {}",
+    MONTHS[month - 1],
+    ordinal(day),
+    year(year_digits.parse().ok()?)
+  ))
+}
+
+fn time(hour: &str, minute: &str, meridiem: Option<&str>) -> Option<String> {
+  let hour = hour.parse::<u64>().ok().filter(|hour| *hour < 24)?;
+  let minute = minute.parse::<u64>().ok().filter(|minute| *minute < 60)?;
+  let mut words = match minute {
+    0 if meridiem.is_none() => format!("{} o'clock", cardinal(hour)),
+    0 => cardinal(hour),
+    1..=9 => format!("{} oh {}", cardinal(hour), cardinal(minute)),
+    _ => format!("{} {}", cardinal(hour), cardinal(minute)),
+  };
+  if let Some(meridiem) = meridiem {
+    words.push_str(if meridiem.eq_ignore_ascii_case("a") {
+      " a m"
+    } else {
+      " p m"
+    });
+  }
+  Some(words)
+}
+
+fn currency(symbol: &str, amount: &str) -> Option<String> {
+  let currency = CURRENCIES
+    .iter()
+    .find(|currency| currency.symbol == symbol)?;
+  let (major, majors) = currency.major;
+  let amount = amount.replace(',', "");
+  let (whole, fraction) = amount.split_once('.').unwrap_or((amount.as_str(), ""));
+  let whole = whole.parse::<u64>().ok()?;
+
+  let (minor, minors) = match currency.minor {
+    Some(minor) if fraction.len() <= 2 => minor,
+    // Amounts without a minor unit, or with more precision than it, are read as decimals
+    _ => return Some(format!("{} {}", number(&amount), majors)),
+  };
+  let cents = format!("{:0<2}", fraction).parse::<u64>().ok()?;
+
+  let mut words = Vec::new();
+  if whole > 0 || cents == 0 {
+    words.push(format!(
+      "{} {}",
+      cardinal(whole),
+      if whole == 1 { major } else { majors }
+    ));
+  }
+  if cents > 0 {
+    words.push(format!(
+      "{} {}",
+      cardinal(cents),
+      if cents == 1 { minor } else { minors }
+    ));
+  }
+  Some(words.join(" and "))
+}
+
+fn quantity(sign: Option<&str>, digits: &str, unit: Option<&str>) -> Option<String> {
+  let mut words = number(digits);
+  if sign.is_some() {
+    words = format!("minus {}", words);
+  }
+  if let Some(unit) = unit {
+    let (_, singular, plural) = UNITS.iter().find(|(symbol, _, _)| *symbol == unit)?;
+    words.push(' ');
+    words.push_str(if digits == "1" { singular } else { plural });
+  }
+  Some(words)
+}
+
+/** The words a matched token is read as, or None to leave it as written */
+fn expand(captures: &Captures, text: &str) -> Option<String> {
+  let found = captures.get(0).unwrap();
+  let group = |name: &str| captures.name(name).map(|found| found.as_str());
+
+  if let (Some(year), Some(month), Some(day)) =
+    (group("iso_year"), group("iso_month"), group("iso_day"))
+  {
+    return date(year, month, day);
+  }
+  if let (Some(month), Some(day), Some(year)) = (group("month"), group("day"), group("year")) {
+    return date(year, month, day);
+  }
+  if let (Some(hour), Some(minute)) = (group("hour"), group("minute")) {
+    return time(hour, minute, group("meridiem"));
+  }
+  if let (Some(symbol), Some(amount)) = (group("currency"), group("amount")) {
+    return currency(symbol, amount);
+  }
+  if let Some(digits) = group("ordinal") {
+    return Some(ordinal(digits.parse().ok()?));
+  }
+
+  let digits = group("number")?;
+  // Numbers running into letters, as in "3D" or "4K", are part of a name
+  if text[found.end()..]
+    .chars()
+    .next()
+    .is_some_and(char::is_alphanumeric)
+  {
+    return None;
+  }
+  // A hyphen joining two words, as in "3-5", isn't a minus sign
+  let sign = group("sign").filter(|_| {
+    !text[..found.start()]
+      .chars()
+      .next_back()
+      .map(char::is_alphanumeric)
+      .unwrap_or(false)
+  });
+  let unit = group("unit").map(str::trim);
+  // A lone four digit number such as "in 1999" is most likely a year
+  if let (None, None, Ok(number @ 1100..=2099)) = (sign, unit, digits.parse::<u64>()) {
+    if digits.len() == 4 {
+      return Some(year(number));
+    }
+  }
+  let words = quantity(sign, digits, unit)?;
+  Some(match group("sign") {
+    Some(hyphen) if sign.is_none() => format!("{}{}", hyphen, words),
+    _ => words,
+  })
+}
+
+/**
+ * Expands numbers, dates, times, currencies, ordinals and units into English words, keeping a
+ * map from each expansion back to the token it replaced
+ */
+pub fn normalize_text(text: &str) -> NormalizedText {
+  let mut normalized = String::new();
+  let mut spans = Vec::new();
+  let mut offset = 0;
+  // Char counts of the text written so far, in the original and normalized text
+  let (mut original_chars, mut normalized_chars) = (0, 0);
+
+  for captures in REGEX_NORMALIZABLE.captures_iter(text) {
+    let found = captures.get(0).unwrap();
+    let words = match expand(&captures, text) {
+      Some(words) => words,
+      None => continue,
+    };
+
+    let unchanged = &text[offset..found.start()];
+    normalized.push_str(unchanged);
+    original_chars += unchanged.chars().count();
+    normalized_chars += unchanged.chars().count();
+
+    let original_length = found.as_str().chars().count();
+    let length = words.chars().count();
+    spans.push(NormalizedSpan {
+      start: normalized_chars as f64,
+      end: (normalized_chars + length) as f64,
+      original_start: original_chars as f64,
+      original_end: (original_chars + original_length) as f64,
+    });
+    normalized.push_str(&words);
+    original_chars += original_length;
+    normalized_chars += length;
+    offset = found.end();
+  }
+  normalized.push_str(&text[offset..]);
+
+  NormalizedText {
+    text: normalized,
+    spans,
+  }
+}
+
+/**
+ * Expands the text for a voice. Expansions are English words, so other languages are refused
+ * rather than having English read in their voice
+ */
+pub fn normalize_for_voice(text: &str, voice: &str) -> Result<NormalizedText> {
+  let language = voice.rsplit('/').next().unwrap_or(voice).to_lowercase();
+  if language != "en" && !language.starts_with("en-") {
+    return Err(Error::InvalidOption(format!(
+      "normalize is only supported for English voices, not {}",
+      voice
+    )));
+  }
+  Ok(normalize_text(text))
+}
+
+/**
+ * Expands numbers, dates, times, currencies, ordinals and units into words, returning the
+ * expanded text along with where each expansion came from in the original
+ */
+#[napi(js_name = "normalizeText")]
+pub fn normalize_text_js(text: String) -> NormalizedText {
+  normalize_text(&text)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{normalize_for_voice, normalize_text, ordinal, year};
+
+  #[test]
+  fn test_normalize_text() {
+    let normalized = normalize_text(
+      "On 2021-03-04 at 10:05pm, 3 km cost $1.50, 50% of 1,200 people came 2nd, e.g. 3.14 or -5°C.",
+    );
+    assert_eq!(
+      normalized.text,
+      "On March fourth twenty twenty one at ten oh five p m, three kilometers cost one dollar and \
+       fifty cents, fifty percent of one thousand two hundred people came second, e.g. three \
+       point one four or minus five degrees Celsius."
+    );
+
+    // "one dollar and fifty cents" maps back to "$1.50"
+    let start = normalized.text.find("one dollar").unwrap();
+    let end = normalized.text.find(", fifty percent").unwrap();
+    assert_eq!(normalized.original_range(start, end), (36, 41));
+    // Words of an expansion widen to its token, while other words map one to one
+    let cost = normalized.text.find("cost").unwrap();
+    assert_eq!(normalized.original_range(cost, cost + 4), (31, 35));
+
+    // Numbers inside names are left as written, and single letter units need a space
+    assert_eq!(
+      normalize_text("A 3D MP3 on a 4K 1st-gen screen took 5s, or 5 s at 2 m").text,
+      "A 3D MP3 on a 4K first-gen screen took 5s, or five seconds at two meters"
+    );
+    assert_eq!(normalize_text("3kg hams").text, "three kilograms hams");
+
+    // Lone years are read as years, while amounts and other numbers aren't
+    assert_eq!(
+      normalize_text("In 1999, 1500 km and 3000 people").text,
+      "In nineteen ninety nine, one thousand five hundred kilometers and three thousand people"
+    );
+
+    assert!(normalize_for_voice("21", "gmw/en-US").is_ok());
+    assert!(normalize_for_voice("21", "es").is_err());
+
+    assert_eq!(ordinal(21), "twenty first");
+    assert_eq!(year(1999), "nineteen ninety nine");
+    assert_eq!(year(2005), "two thousand five");
+    assert_eq!(year(1905), "nineteen oh five");
+  }
+}
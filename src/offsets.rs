@@ -0,0 +1,139 @@
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{Chunk, NestedChunk};
+
+#[napi]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub enum OffsetUnit {
+  /// Unicode scalar values, as Rust's `chars` counts them
+  #[default]
+  Chars,
+  /// UTF-16 code units, as JavaScript strings are indexed
+  Utf16,
+  /// UTF-8 bytes
+  Utf8Bytes,
+  /// Extended grapheme clusters, so an emoji or accented letter counts once
+  Graphemes,
+}
+
+/** Converts char offsets into a text to another unit */
+pub struct OffsetMap {
+  /// Offset in the target unit of each char, plus one past the end
+  offsets: Vec<usize>,
+  /// Whether each char starts a new unit. Only chars inside a grapheme cluster don't
+  boundaries: Vec<bool>,
+}
+
+impl OffsetMap {
+  pub fn new(text: &str, unit: OffsetUnit) -> Self {
+    let mut offsets = Vec::with_capacity(text.len() + 1);
+    let mut boundaries = Vec::with_capacity(text.len() + 1);
+    let mut offset = 0;
+    match unit {
+      OffsetUnit::Graphemes => {
+        for grapheme in text.graphemes(true) {
+          for (index, _) in grapheme.chars().enumerate() {
+            offsets.push(offset);
+            boundaries.push(index == 0);
+          }
+          offset += 1;
+        }
+      }
+      _ => {
+        for char in text.chars() {
+          offsets.push(offset);
+          boundaries.push(true);
+          offset += match unit {
+            OffsetUnit::Utf16 => char.len_utf16(),
+            OffsetUnit::Utf8Bytes => char.len_utf8(),
+            _ => 1,
+          };
+        }
+      }
+    }
+    offsets.push(offset);
+    boundaries.push(true);
+    Self {
+      offsets,
+      boundaries,
+    }
+  }
+
+  /** Converts a start offset, rounding down to the unit it falls in */
+  pub fn start(&self, char_offset: f64) -> f64 {
+    let index = (char_offset.max(0.0) as usize).min(self.offsets.len() - 1);
+    self.offsets[index] as f64
+  }
+
+  /** Converts an end offset, rounding up so the unit it falls in is covered */
+  pub fn end(&self, char_offset: f64) -> f64 {
+    let index = (char_offset.max(0.0) as usize).min(self.offsets.len() - 1);
+    if self.boundaries[index] {
+      self.offsets[index] as f64
+    } else {
+      (self.offsets[index] + 1) as f64
+    }
+  }
+}
+
+/** Converts the offsets of a word and its parts */
+fn convert_word(map: &OffsetMap, word: &mut Chunk) {
+  word.start = map.start(word.start);
+  word.end = map.end(word.end);
+  for part in word.parts.iter_mut().flatten() {
+    convert_word(map, part);
+  }
+}
+
+/**
+ * Converts the char offsets of the chunk, its words and its paragraphs and sentences into `unit`.
+ * Phoneme chunks index the phoneme list rather than the text, so they're left alone
+ */
+pub fn convert_offsets(text: &str, unit: OffsetUnit, chunk: &mut NestedChunk) {
+  if unit == OffsetUnit::Chars {
+    return;
+  }
+  let map = OffsetMap::new(text, unit);
+  chunk.start = map.start(chunk.start);
+  chunk.end = map.end(chunk.end);
+  for word in &mut chunk.chunks {
+    convert_word(&map, word);
+  }
+  for paragraph in chunk.paragraphs.iter_mut().flatten() {
+    paragraph.start = map.start(paragraph.start);
+    paragraph.end = map.end(paragraph.end);
+    for sentence in &mut paragraph.sentences {
+      sentence.start = map.start(sentence.start);
+      sentence.end = map.end(sentence.end);
+      for word in &mut sentence.chunks {
+        convert_word(&map, word);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{OffsetMap, OffsetUnit};
+
+  #[test]
+  fn test_offset_map() {
+    // "👍🏽" is two chars, four UTF-16 code units, eight bytes and one grapheme
+    let text = "a👍🏽 é b";
+    let word = (6.0, 7.0);
+    let convert = |unit| {
+      let map = OffsetMap::new(text, unit);
+      (map.start(word.0), map.end(word.1))
+    };
+    assert_eq!(convert(OffsetUnit::Chars), (6.0, 7.0));
+    assert_eq!(convert(OffsetUnit::Utf16), (8.0, 9.0));
+    assert_eq!(convert(OffsetUnit::Utf8Bytes), (13.0, 14.0));
+    assert_eq!(convert(OffsetUnit::Graphemes), (5.0, 6.0));
+
+    // "é" written as e and a combining accent is one grapheme
+    let map = OffsetMap::new("e\u{301}x", OffsetUnit::Graphemes);
+    assert_eq!((map.start(1.0), map.end(1.0)), (0.0, 1.0));
+  }
+}
@@ -1,5 +1,12 @@
+use crate::blocking;
+use crate::boundary::PRESERVED_PUNCTUATION;
+use crate::cache::{self, CacheKey};
+use crate::error::Result;
+use crate::phonetics::notation::PhonemeAlphabet;
 use crate::phonetics::punctuation::{extract_punctuation, restore_punctuations};
-use crate::text_to_phonemes;
+use crate::speaker::{self, Session};
+use crate::voice::DEFAULT_VOICE;
+use crate::PhonemizeOptions;
 use napi_derive::napi;
 use regex::Regex;
 
@@ -13,6 +20,9 @@ const PHONEME_SET: [&str; 126] = [
   "ʡ", "ʢ", "ˈ", "ˌ", "ː", "ˑ", "˞", "β", "θ", "χ", "ᵻ", "ⱱ",
 ];
 
+/// Texts phonemized per speaker job in a batch, so a large batch doesn't hold up other calls
+const BATCH_CHUNK_SIZE: usize = 64;
+
 // https://github.com/espeak-ng/espeak-ng/issues/694
 fn remove_additional_separators(string: &str) -> String {
   Regex::new("_+")
@@ -27,17 +37,30 @@ fn remove_line_breaks(string: &str) -> String {
 fn remove_extra_spaces(string: &str) -> String {
   string.replace("  ", " ")
 }
-fn remove_non_phonetic_chars(string: &str) -> String {
+fn is_phonetic_char(char: char, options: &PhonemizeOptions) -> bool {
+  if options.tie().char() == Some(char) {
+    return true;
+  }
+  match options.alphabet() {
+    PhonemeAlphabet::Ipa => PHONEME_SET.contains(&(char.to_string().as_str())),
+    // espeak's mnemonics and Kirshenbaum are both written in printable ASCII
+    PhonemeAlphabet::Ascii | PhonemeAlphabet::Kirshenbaum => {
+      char.is_ascii() && !char.is_ascii_control()
+    }
+  }
+}
+fn remove_non_phonetic_chars(string: &str, options: &PhonemizeOptions) -> String {
   string
     .chars()
-    .filter(|char| PHONEME_SET.contains(&(char.to_string().as_str())))
+    .filter(|char| is_phonetic_char(*char, options))
     .collect()
 }
 
-fn sanitize_espeak_output(string: &str) -> String {
-  remove_non_phonetic_chars(&remove_extra_spaces(&remove_line_breaks(
-    &remove_additional_separators(string),
-  )))
+fn sanitize_espeak_output(string: &str, options: &PhonemizeOptions) -> String {
+  remove_non_phonetic_chars(
+    &remove_extra_spaces(&remove_line_breaks(&remove_additional_separators(string))),
+    options,
+  )
 }
 
 // TODO: Handle pure whitespace
@@ -64,30 +87,160 @@ fn collapse_whitespace(string: &str) -> String {
     .to_string()
 }
 
-pub fn to_phonetics(text: &str) -> String {
-  sanitize_espeak_output(&text_to_phonemes(text))
+/** Converts the text to phonemes with the session's current voice, using the cache if enabled */
+pub fn to_phonetics_with(
+  session: &mut Session,
+  text: &str,
+  options: &PhonemizeOptions,
+) -> Result<String> {
+  let key = CacheKey::new(text, session.voice().unwrap_or_default(), options);
+  if let Some(phonemes) = cache::get(&key) {
+    return Ok(phonemes);
+  }
+
+  let phonemes = sanitize_espeak_output(&session.text_to_phonemes(text, options)?, options);
+  cache::insert(key, phonemes.clone());
+  Ok(phonemes)
+}
+
+pub fn to_phonetics(text: &str, voice: &str, options: &PhonemizeOptions) -> Result<String> {
+  let text = text.to_string();
+  let voice = voice.to_string();
+  let options = options.clone();
+  speaker::run(move |session| {
+    session.set_voice(&voice)?;
+    to_phonetics_with(session, &text, &options)
+  })
 }
 
-pub async fn string_to_phonetics(text: &str, preserve_punctuation: bool) -> String {
-  if preserve_punctuation {
+/**
+ * Converts the text to phonemes with the session's current voice, optionally keeping its
+ * punctuation and boundary whitespace. Punctuation can't be split out of SSML without breaking
+ * its markup, so espeak's own handling of punctuation is used for SSML instead
+ */
+pub fn string_to_phonetics_with(
+  session: &mut Session,
+  text: &str,
+  preserve_punctuation: bool,
+  options: &PhonemizeOptions,
+) -> Result<String> {
+  if preserve_punctuation && !options.ssml.unwrap_or(false) {
     let res = Regex::new("([0-9]),([0-9])")
       .unwrap()
       .replace(text, "$1$2")
       .to_string();
-    let (split_texts, punctuations) = extract_punctuation(&res);
-    let phonemized_texts = split_texts.iter().map(|s| to_phonetics(s)).collect();
+    let boundaries = options
+      .boundaries
+      .as_ref()
+      .unwrap_or(&PRESERVED_PUNCTUATION);
+    let (split_texts, punctuations) = extract_punctuation(&res, boundaries);
+    let phonemized_texts = split_texts
+      .iter()
+      .map(|s| to_phonetics_with(session, s, options))
+      .collect::<Result<Vec<_>>>()?;
 
     let combined_phonemized_text = restore_punctuations(punctuations, phonemized_texts);
 
-    return remove_line_breaks(&collapse_whitespace(&preserve_boundary_whitespace(
-      text,
-      &combined_phonemized_text,
+    return Ok(remove_line_breaks(&collapse_whitespace(
+      &preserve_boundary_whitespace(text, &combined_phonemized_text),
     )));
   }
-  to_phonetics(text)
+  to_phonetics_with(session, text, options)
+}
+
+pub fn string_to_phonetics(
+  text: &str,
+  preserve_punctuation: bool,
+  voice: &str,
+  options: &PhonemizeOptions,
+) -> Result<String> {
+  let text = text.to_string();
+  let voice = voice.to_string();
+  let options = options.clone();
+  speaker::run(move |session| {
+    session.set_voice(&voice)?;
+    string_to_phonetics_with(session, &text, preserve_punctuation, &options)
+  })
+}
+
+/**
+ * Phonemizes the texts a chunk at a time, each chunk in one speaker job so the batch pays for
+ * queueing and switching voice once per chunk while other calls can run between chunks. Results
+ * are in the order of the texts, each failing on its own
+ */
+pub fn phonemize_batch(
+  texts: Vec<String>,
+  preserve_punctuation: bool,
+  voice: &str,
+  options: &PhonemizeOptions,
+) -> Result<Vec<Result<String>>> {
+  let mut results = Vec::with_capacity(texts.len());
+  for chunk in texts.chunks(BATCH_CHUNK_SIZE) {
+    let chunk = chunk.to_vec();
+    let voice = voice.to_string();
+    let options = options.clone();
+    results.extend(speaker::run(move |session| {
+      session.set_voice(&voice)?;
+      Ok(
+        chunk
+          .iter()
+          .map(|text| string_to_phonetics_with(session, text, preserve_punctuation, &options))
+          .collect::<Vec<_>>(),
+      )
+    })?);
+  }
+  Ok(results)
 }
 
 #[napi]
-pub async fn phonemize(text: String, preserve_punctuation: bool) -> String {
-  string_to_phonetics(text.as_str(), preserve_punctuation).await
+pub async fn phonemize(
+  text: String,
+  preserve_punctuation: bool,
+  voice: Option<String>,
+  options: Option<PhonemizeOptions>,
+) -> napi::Result<String> {
+  let voice = voice.unwrap_or_else(|| DEFAULT_VOICE.to_string());
+  let options = options.unwrap_or_default();
+  blocking::run(move || string_to_phonetics(text.as_str(), preserve_punctuation, &voice, &options))
+    .await
+}
+
+#[napi(object)]
+pub struct PhonemizeResult {
+  pub phonemes: Option<String>,
+  /// Why the text couldn't be phonemized, in the same "CODE: message" form as thrown errors
+  pub error: Option<String>,
+}
+
+/**
+ * Phonemizes many texts in one call, returning a result for each in the same order. A text
+ * which fails to phonemize sets its `error` rather than rejecting the whole batch
+ */
+#[napi(js_name = "phonemizeBatch")]
+pub async fn phonemize_batch_async(
+  texts: Vec<String>,
+  preserve_punctuation: bool,
+  voice: Option<String>,
+  options: Option<PhonemizeOptions>,
+) -> napi::Result<Vec<PhonemizeResult>> {
+  let voice = voice.unwrap_or_else(|| DEFAULT_VOICE.to_string());
+  let options = options.unwrap_or_default();
+  let results =
+    blocking::run(move || phonemize_batch(texts, preserve_punctuation, &voice, &options)).await?;
+
+  Ok(
+    results
+      .into_iter()
+      .map(|result| match result {
+        Ok(phonemes) => PhonemizeResult {
+          phonemes: Some(phonemes),
+          error: None,
+        },
+        Err(err) => PhonemizeResult {
+          phonemes: None,
+          error: Some(napi::Error::from(err).reason),
+        },
+      })
+      .collect(),
+  )
 }
@@ -1,2 +1,6 @@
 pub mod internal;
+pub mod notation;
 pub mod punctuation;
+pub mod stream;
+pub mod syllables;
+pub mod words;
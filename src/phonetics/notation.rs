@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+use espeakng::bindings;
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use once_cell::sync::Lazy;
+
+use crate::PhonemizeOptions;
+
+#[napi]
+#[derive(Debug, Default, Hash, PartialEq, Eq)]
+pub enum PhonemeAlphabet {
+  /// International Phonetic Alphabet
+  #[default]
+  Ipa,
+  /// espeak's ASCII phoneme mnemonics, as printed by `espeak-ng -x`
+  Ascii,
+  /// Kirshenbaum ASCII-IPA, converted from espeak's IPA output
+  Kirshenbaum,
+}
+
+#[napi]
+#[derive(Debug, Default, Hash, PartialEq, Eq)]
+pub enum PhonemeTie {
+  /// Multi-letter phonemes such as tʃ are written as separate letters
+  #[default]
+  None,
+  /// Joins multi-letter phonemes with a tie bar (U+0361), e.g. t͡ʃ
+  TieBar,
+  /// Joins multi-letter phonemes with a zero width joiner (U+200D)
+  ZeroWidthJoiner,
+}
+
+impl PhonemeTie {
+  pub fn char(&self) -> Option<char> {
+    match self {
+      PhonemeTie::None => None,
+      PhonemeTie::TieBar => Some('\u{361}'),
+      PhonemeTie::ZeroWidthJoiner => Some('\u{200D}'),
+    }
+  }
+}
+
+impl PhonemizeOptions {
+  pub fn alphabet(&self) -> PhonemeAlphabet {
+    self.alphabet.unwrap_or_default()
+  }
+
+  /** Kirshenbaum has no notion of ties, so they're only applied to IPA and ASCII output */
+  pub fn tie(&self) -> PhonemeTie {
+    match self.alphabet() {
+      PhonemeAlphabet::Kirshenbaum => PhonemeTie::None,
+      _ => self.tie.unwrap_or_default(),
+    }
+  }
+}
+
+fn tie_bits(tie: PhonemeTie) -> u32 {
+  match tie.char() {
+    // Bit 7 enables the tie, whose character is stored in bits 8-23
+    Some(char) => bindings::espeakPHONEMES_TIE | ((char as u32) << 8),
+    None => 0,
+  }
+}
+
+/** The `phonememode` argument of `espeak_TextToPhonemes` for the options */
+pub fn phoneme_mode(options: &PhonemizeOptions) -> u32 {
+  let alphabet = match options.alphabet() {
+    PhonemeAlphabet::Ipa | PhonemeAlphabet::Kirshenbaum => bindings::espeakPHONEMES_IPA,
+    PhonemeAlphabet::Ascii => 0,
+  };
+  alphabet | tie_bits(options.tie())
+}
+
+/** The `phonememode` argument of `espeak_SetPhonemeTrace` for the options */
+pub fn trace_mode(options: &PhonemizeOptions) -> u32 {
+  let alphabet = match options.alphabet() {
+    PhonemeAlphabet::Ipa | PhonemeAlphabet::Kirshenbaum => bindings::espeakPHONEMES_IPA,
+    PhonemeAlphabet::Ascii => bindings::espeakPHONEMES_SHOW,
+  };
+  alphabet | tie_bits(options.tie())
+}
+
+// https://en.wikipedia.org/wiki/Kirshenbaum
+pub static IPA_TO_KIRSHENBAUM: Lazy<HashMap<char, &'static str>> = Lazy::new(|| {
+  HashMap::from([
+    ('ɐ', "a#"),
+    ('ɑ', "A"),
+    ('ɒ', "A."),
+    ('æ', "&"),
+    ('ɓ', "b`"),
+    ('β', "B"),
+    ('ç', "C"),
+    ('ɕ', "s;"),
+    ('ð', "D"),
+    ('ɖ', "d."),
+    ('ɗ', "d`"),
+    ('ə', "@"),
+    ('ɚ', "R"),
+    ('ɘ', "@<umd>"),
+    ('ɛ', "E"),
+    ('ɜ', "V\""),
+    ('ɞ', "O\""),
+    ('ɡ', "g"),
+    ('ɢ', "G"),
+    ('ɣ', "Q"),
+    ('ɤ', "o-"),
+    ('ħ', "H"),
+    ('ɦ', "h<?>"),
+    ('ɨ', "i\""),
+    ('ɪ', "I"),
+    ('ᵻ', "I\""),
+    ('ʝ', "C<vcd>"),
+    ('ɟ', "J"),
+    ('ɫ', "l<vel>"),
+    ('ɬ', "s<lat>"),
+    ('ɭ', "l."),
+    ('ɮ', "z<lat>"),
+    ('ʟ', "L"),
+    ('ʎ', "l^"),
+    ('ɯ', "u-"),
+    ('ɰ', "j<vel>"),
+    ('ɱ', "M"),
+    ('ɲ', "n^"),
+    ('ŋ', "N"),
+    ('ɳ', "n."),
+    ('ɴ', "n\""),
+    ('ø', "Y"),
+    ('ɵ', "@."),
+    ('œ', "W"),
+    ('ɶ', "a."),
+    ('ɔ', "O"),
+    ('ɸ', "P"),
+    ('ɹ', "r"),
+    ('ɻ', "r."),
+    ('ɾ', "*"),
+    ('ɽ', "*."),
+    ('ʀ', "r\""),
+    ('ʁ', "g\""),
+    ('ʂ', "s."),
+    ('ʃ', "S"),
+    ('θ', "T"),
+    ('ʈ', "t."),
+    ('ʉ', "u\""),
+    ('ʊ', "U"),
+    ('ʋ', "r<lbd>"),
+    ('ʌ', "V"),
+    ('ʍ', "w<vls>"),
+    ('χ', "X"),
+    ('ʏ', "I."),
+    ('ʐ', "z."),
+    ('ʑ', "z;"),
+    ('ʒ', "Z"),
+    ('ʔ', "?"),
+    ('ʕ', "H<vcd>"),
+    ('ɥ', "w<frt>"),
+    ('ː', ":"),
+    ('ˑ', ";"),
+    ('ˈ', "'"),
+    ('ˌ', ","),
+    ('\u{303}', "~"),
+    ('˞', "<r>"),
+  ])
+});
+
+/** Converts espeak's IPA output to Kirshenbaum, leaving plain ASCII such as "p" or "k" as is */
+pub fn ipa_to_kirshenbaum(ipa: &str) -> String {
+  ipa
+    .chars()
+    .map(|char| match IPA_TO_KIRSHENBAUM.get(&char) {
+      Some(kirshenbaum) => kirshenbaum.to_string(),
+      None if char.is_ascii() => char.to_string(),
+      None => String::new(),
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{ipa_to_kirshenbaum, phoneme_mode, PhonemeAlphabet, PhonemeTie};
+  use crate::PhonemizeOptions;
+
+  #[test]
+  fn test_ipa_to_kirshenbaum() {
+    assert_eq!(ipa_to_kirshenbaum("həlˈoʊ wˈɜːld"), "h@l'oU w'V\":ld");
+    assert_eq!(ipa_to_kirshenbaum("ðə tʃˈɜːtʃ"), "D@ tS'V\":tS");
+  }
+
+  #[test]
+  fn test_phoneme_mode() {
+    let options = PhonemizeOptions {
+      alphabet: Some(PhonemeAlphabet::Ipa),
+      tie: Some(PhonemeTie::TieBar),
+      ..Default::default()
+    };
+    assert_eq!(phoneme_mode(&options), 0x2 | 0x80 | (0x361 << 8));
+
+    let options = PhonemizeOptions {
+      alphabet: Some(PhonemeAlphabet::Kirshenbaum),
+      tie: Some(PhonemeTie::ZeroWidthJoiner),
+      ..Default::default()
+    };
+    assert_eq!(phoneme_mode(&options), 0x2);
+  }
+}
@@ -1,18 +1,32 @@
-use regex::Regex;
+use crate::boundary::BoundaryProfile;
 
-pub fn extract_punctuation(text: &str) -> (Vec<String>, Vec<String>) {
-  let split_text_regex = Regex::new("([!(),-.:;?]+)").unwrap();
-  //   let (texts, punctuations) =
-  let splits = split_text_regex.split(text).collect::<Vec<&str>>();
-  let captures = split_text_regex
-    .find_iter(text)
-    .map(|m| m.as_str())
-    .collect::<Vec<&str>>();
+/**
+ * Splits runs of the profile's punctuation out of the text, returning the text between them and
+ * the runs themselves. Joiners inside a word, such as the hyphen in "well-known", are left in
+ */
+pub fn extract_punctuation(text: &str, profile: &BoundaryProfile) -> (Vec<String>, Vec<String>) {
+  let chars = text.chars().collect::<Vec<_>>();
+  let mut texts = vec![String::new()];
+  let mut punctuations: Vec<String> = Vec::new();
+  let mut in_punctuation = false;
 
-  (
-    splits.iter().map(|i| (*i).to_owned()).collect(),
-    captures.iter().map(|i| (*i).to_owned()).collect(),
-  )
+  for (index, char) in chars.iter().copied().enumerate() {
+    let previous = index.checked_sub(1).map(|previous| chars[previous]);
+    let is_punctuation =
+      profile.is_punctuation(char) && !profile.joins(previous, char, chars.get(index + 1).copied());
+    match (is_punctuation, in_punctuation) {
+      (true, true) => punctuations.last_mut().unwrap().push(char),
+      (true, false) => punctuations.push(char.to_string()),
+      (false, true) => texts.push(char.to_string()),
+      (false, false) => texts.last_mut().unwrap().push(char),
+    }
+    in_punctuation = is_punctuation;
+  }
+  if in_punctuation {
+    texts.push(String::new());
+  }
+
+  (texts, punctuations)
 }
 
 pub fn whitespace_replacer(text: &str, replacer: &str, last_remaining_chars: &mut usize) -> String {
@@ -50,13 +64,14 @@ pub fn restore_punctuations(punctuations: Vec<String>, texts: Vec<String>) -> St
 
 #[cfg(test)]
 mod tests {
+  use crate::boundary::{BoundaryProfile, PRESERVED_PUNCTUATION};
   use crate::phonetics::punctuation::restore_punctuations;
 
   use super::extract_punctuation;
   #[test]
   fn test_extract_punctuation() {
     let text = "Hey, this is a, with a bunch of punctuations!";
-    let (texts, punctuations) = extract_punctuation(text);
+    let (texts, punctuations) = extract_punctuation(text, &BoundaryProfile::default());
     let result_text = vec!["Hey", " this is a", " with a bunch of punctuations", ""];
     let result_punctuations = vec![",", ",", "!"];
 
@@ -64,10 +79,20 @@ mod tests {
     assert_eq!(punctuations, result_punctuations);
   }
 
+  #[test]
+  fn test_extract_punctuation_default() {
+    // Phonemization's default keeps the original punctuation, splitting hyphens out of words
+    let text = "A well-known \"quote\" — (really)";
+    let (texts, punctuations) = extract_punctuation(text, &PRESERVED_PUNCTUATION);
+
+    assert_eq!(texts, vec!["A well", "known \"quote\" — ", "really", ""]);
+    assert_eq!(punctuations, vec!["-", "(", ")"]);
+  }
+
   #[test]
   fn test_restore_punctuations() {
     let text = "Hey, this is a, with a bunch of punctuations!";
-    let (texts, punctuations) = extract_punctuation(text);
+    let (texts, punctuations) = extract_punctuation(text, &BoundaryProfile::default());
 
     let result = restore_punctuations(punctuations, texts);
     println!("{}", result);
@@ -0,0 +1,103 @@
+use std::sync::{Arc, Mutex};
+
+use napi_derive::napi;
+
+use crate::blocking;
+use crate::error::Result;
+use crate::phonetics::internal::string_to_phonetics;
+use crate::segment::{segment_sentences, SegmentOptions, SentenceSpan};
+use crate::voice::DEFAULT_VOICE;
+use crate::PhonemizeOptions;
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhonemizedSentence {
+  pub text: String,
+  /// Offset of the sentence in the source text, in chars
+  pub start: f64,
+  pub end: f64,
+  pub phonemes: String,
+}
+
+/** Phonemizes a text lazily, one sentence per speaker job */
+pub struct PhonemeStream {
+  text: String,
+  sentences: std::vec::IntoIter<SentenceSpan>,
+  preserve_punctuation: bool,
+  voice: String,
+  options: PhonemizeOptions,
+}
+
+impl PhonemeStream {
+  pub fn new(
+    text: String,
+    preserve_punctuation: bool,
+    voice: &str,
+    options: &PhonemizeOptions,
+  ) -> PhonemeStream {
+    PhonemeStream {
+      sentences: segment_sentences(&text, &SegmentOptions::default()).into_iter(),
+      text,
+      preserve_punctuation,
+      voice: voice.to_string(),
+      options: options.clone(),
+    }
+  }
+}
+
+impl Iterator for PhonemeStream {
+  type Item = Result<PhonemizedSentence>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let span = self.sentences.next()?;
+    let text = &self.text[span.byte_start..span.byte_end];
+    Some(
+      string_to_phonetics(text, self.preserve_punctuation, &self.voice, &self.options).map(
+        |phonemes| PhonemizedSentence {
+          text: text.to_string(),
+          start: span.start as f64,
+          end: span.end as f64,
+          phonemes,
+        },
+      ),
+    )
+  }
+}
+
+/**
+ * Phonemizes a text a sentence at a time, only phonemizing each sentence once it's asked for so
+ * a slow consumer is never flooded. Backs `phonemizeSentences` and `phonemizeStream`
+ */
+#[napi]
+pub struct PhonemeSentences {
+  stream: Arc<Mutex<PhonemeStream>>,
+}
+
+#[napi]
+impl PhonemeSentences {
+  #[napi(constructor)]
+  pub fn new(
+    text: String,
+    preserve_punctuation: bool,
+    voice: Option<String>,
+    options: Option<PhonemizeOptions>,
+  ) -> Self {
+    let voice = voice.unwrap_or_else(|| DEFAULT_VOICE.to_string());
+    let options = options.unwrap_or_default();
+    PhonemeSentences {
+      stream: Arc::new(Mutex::new(PhonemeStream::new(
+        text,
+        preserve_punctuation,
+        &voice,
+        &options,
+      ))),
+    }
+  }
+
+  /** Phonemizes the next sentence, resolving to null once every sentence has been returned */
+  #[napi]
+  pub async fn next(&self) -> napi::Result<Option<PhonemizedSentence>> {
+    let stream = self.stream.clone();
+    blocking::run(move || stream.lock().unwrap().next().transpose()).await
+  }
+}
@@ -0,0 +1,294 @@
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use crate::blocking;
+use crate::error::Result;
+use crate::phonetics::internal::to_phonetics;
+use crate::phonetics::notation::PhonemeAlphabet;
+use crate::voice::DEFAULT_VOICE;
+use crate::{PhonemizeOptions, COMBINED_PHONEME_MAPPING};
+
+#[napi]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub enum Stress {
+  #[default]
+  None,
+  Primary,
+  Secondary,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Syllable {
+  /// The syllable's phonemes, without stress marks
+  pub phonemes: String,
+  pub stress: Stress,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhonemizedWord {
+  /// The word's phonemes as espeak wrote them, including stress marks. Words espeak runs
+  /// together, such as "ɪnðə", are split apart
+  pub phonemes: String,
+  pub syllables: Vec<Syllable>,
+}
+
+const VOWELS: [char; 30] = [
+  'a', 'e', 'i', 'o', 'u', 'y', 'æ', 'ɐ', 'ɑ', 'ɒ', 'ɔ', 'ə', 'ɚ', 'ɛ', 'ɜ', 'ɝ', 'ɞ', 'ɘ', 'ɵ',
+  'ɤ', 'ɯ', 'ɨ', 'ʉ', 'ɪ', 'ʏ', 'ʊ', 'ʌ', 'ø', 'œ', 'ᵻ',
+];
+/// Vowels which end a diphthong when following another vowel, as in aɪ, oʊ or ɪə
+const OFFGLIDES: [char; 3] = ['ɪ', 'ʊ', 'ə'];
+/// Letters which modify the phoneme before them rather than starting a new one
+const MODIFIERS: [char; 9] = ['ː', 'ˑ', 'ʰ', 'ʲ', 'ʷ', 'ˠ', 'ˤ', '˞', '\u{200D}'];
+/// Two consonant clusters which may start a syllable, checked before splitting a cluster
+const ONSET_CLUSTERS: [&str; 27] = [
+  "pl", "pɹ", "bl", "bɹ", "tɹ", "dɹ", "kl", "kɹ", "kw", "ɡl", "ɡɹ", "fl", "fɹ", "θɹ", "ʃɹ", "sp",
+  "st", "sk", "sm", "sn", "sl", "sw", "tw", "dw", "pj", "kj", "tʃ",
+];
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+  Vowel(String),
+  Consonant(String),
+  Stress(Stress),
+}
+
+fn is_combining(char: char) -> bool {
+  ('\u{300}'..='\u{36F}').contains(&char)
+}
+
+/** Splits an IPA word into phonemes, keeping modifiers and tied letters with their phoneme */
+fn segment(word: &str) -> Vec<Segment> {
+  let mut segments: Vec<Segment> = Vec::new();
+  let mut tied = false;
+
+  for char in word.chars() {
+    let joins_previous = tied || MODIFIERS.contains(&char) || is_combining(char);
+    // Tie bars join the phoneme before them with the one after
+    tied = char == '\u{361}' || char == '\u{200D}';
+
+    match segments.last_mut() {
+      Some(Segment::Vowel(phoneme)) | Some(Segment::Consonant(phoneme)) if joins_previous => {
+        phoneme.push(char);
+        continue;
+      }
+      _ => {}
+    }
+
+    segments.push(match char {
+      'ˈ' => Segment::Stress(Stress::Primary),
+      'ˌ' => Segment::Stress(Stress::Secondary),
+      _ if VOWELS.contains(&char) => Segment::Vowel(char.to_string()),
+      _ => Segment::Consonant(char.to_string()),
+    });
+  }
+
+  // Merge diphthongs into a single nucleus
+  let mut merged: Vec<Segment> = Vec::new();
+  for segment in segments {
+    if let (Some(Segment::Vowel(previous)), Segment::Vowel(phoneme)) = (merged.last_mut(), &segment)
+    {
+      let is_offglide = phoneme
+        .chars()
+        .next()
+        .map(|char| OFFGLIDES.contains(&char))
+        .unwrap_or(false);
+      if is_offglide && previous.chars().count() == 1 {
+        previous.push_str(phoneme);
+        continue;
+      }
+    }
+    merged.push(segment);
+  }
+  merged
+}
+
+/** How many consonants of the cluster between two nuclei belong to the second syllable */
+fn onset_length(cluster: &[&str]) -> usize {
+  match cluster.len() {
+    0 => 0,
+    1 => 1,
+    length => {
+      let last_two = cluster[length - 2..].concat();
+      if ONSET_CLUSTERS.contains(&last_two.as_str()) {
+        2
+      } else {
+        1
+      }
+    }
+  }
+}
+
+/**
+ * Splits an IPA word from espeak into syllables using its vowels as nuclei. Consonants between
+ * two vowels start the next syllable when they form a valid onset, and stress marks apply to
+ * the syllable of the vowel following them
+ */
+pub fn syllabify(word: &str) -> Vec<Syllable> {
+  let segments = segment(word);
+
+  // Each syllable's phonemes and stress, opened by its nucleus
+  let mut syllables: Vec<(Vec<&str>, Stress)> = Vec::new();
+  let mut cluster: Vec<&str> = Vec::new();
+  let mut stress = Stress::None;
+
+  for segment in &segments {
+    match segment {
+      Segment::Stress(mark) => stress = *mark,
+      Segment::Consonant(phoneme) => cluster.push(phoneme),
+      Segment::Vowel(phoneme) => {
+        let onset = match syllables.last_mut() {
+          Some((previous, _)) => {
+            let coda_length = cluster.len() - onset_length(&cluster);
+            previous.extend(cluster.drain(..coda_length));
+            std::mem::take(&mut cluster)
+          }
+          None => std::mem::take(&mut cluster),
+        };
+
+        let mut phonemes = onset;
+        phonemes.push(phoneme);
+        syllables.push((phonemes, stress));
+        stress = Stress::None;
+      }
+    }
+  }
+
+  // Trailing consonants close the last syllable, or form one if there were no vowels
+  match syllables.last_mut() {
+    Some((previous, _)) => previous.append(&mut cluster),
+    None if !cluster.is_empty() => syllables.push((cluster, stress)),
+    None => {}
+  }
+
+  syllables
+    .into_iter()
+    .map(|(phonemes, stress)| Syllable {
+      phonemes: phonemes.concat(),
+      stress,
+    })
+    .collect()
+}
+
+/**
+ * Splits words espeak runs together, such as "ɪnðə" for "in the", back into their words. Stress
+ * marks are ignored when looking the word up, and stay with the word they precede
+ */
+fn split_combined(word: String) -> Vec<String> {
+  let unstressed = word
+    .chars()
+    .filter(|char| !matches!(char, 'ˈ' | 'ˌ'))
+    .collect::<String>();
+  let first_length = match COMBINED_PHONEME_MAPPING.get(unstressed.as_str()) {
+    Some([first, _]) => first.chars().count(),
+    None => return vec![word],
+  };
+
+  let mut first = String::new();
+  let mut chars = word.chars().peekable();
+  while first
+    .chars()
+    .filter(|char| !matches!(char, 'ˈ' | 'ˌ'))
+    .count()
+    < first_length
+  {
+    first.extend(chars.next());
+  }
+  vec![first, chars.collect()]
+}
+
+/** Phonemizes the text into IPA words, each split into stressed syllables */
+pub fn phonemize_syllables(
+  text: &str,
+  voice: &str,
+  options: &PhonemizeOptions,
+) -> Result<Vec<PhonemizedWord>> {
+  let options = PhonemizeOptions {
+    alphabet: Some(PhonemeAlphabet::Ipa),
+    ..options.clone()
+  };
+  let phonemes = to_phonetics(text, voice, &options)?;
+
+  Ok(
+    phonemes
+      .split_whitespace()
+      .map(|word| {
+        word
+          .chars()
+          .filter(|char| !"!'(),-.:;?".contains(*char))
+          .collect::<String>()
+      })
+      .filter(|word| !word.is_empty())
+      .flat_map(split_combined)
+      .map(|word| PhonemizedWord {
+        syllables: syllabify(&word),
+        phonemes: word,
+      })
+      .collect(),
+  )
+}
+
+#[napi(js_name = "phonemizeSyllables")]
+pub async fn phonemize_syllables_async(
+  text: String,
+  voice: Option<String>,
+  options: Option<PhonemizeOptions>,
+) -> napi::Result<Vec<PhonemizedWord>> {
+  let voice = voice.unwrap_or_else(|| DEFAULT_VOICE.to_string());
+  let options = options.unwrap_or_default();
+  blocking::run(move || phonemize_syllables(text.as_str(), &voice, &options)).await
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{split_combined, syllabify, Stress, Syllable};
+
+  fn syllables(word: &str) -> Vec<(String, Stress)> {
+    syllabify(word)
+      .into_iter()
+      .map(|Syllable { phonemes, stress }| (phonemes, stress))
+      .collect()
+  }
+
+  #[test]
+  fn test_syllabify() {
+    assert_eq!(
+      syllables("həlˈoʊ"),
+      vec![
+        ("hə".to_string(), Stress::None),
+        ("loʊ".to_string(), Stress::Primary)
+      ]
+    );
+    assert_eq!(
+      syllables("ˌɪntɚnˈæʃənəl"),
+      vec![
+        ("ɪn".to_string(), Stress::Secondary),
+        ("tɚ".to_string(), Stress::None),
+        ("næ".to_string(), Stress::Primary),
+        ("ʃə".to_string(), Stress::None),
+        ("nəl".to_string(), Stress::None),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_syllabify_clusters_and_consonants() {
+    assert_eq!(
+      syllables("ɛkstɹˈiːm"),
+      vec![
+        ("ɛks".to_string(), Stress::None),
+        ("tɹiːm".to_string(), Stress::Primary)
+      ]
+    );
+    assert_eq!(syllables("ʃ"), vec![("ʃ".to_string(), Stress::None)]);
+  }
+
+  #[test]
+  fn test_split_combined() {
+    assert_eq!(split_combined("ɪnðə".to_string()), vec!["ɪn", "ðə"]);
+    assert_eq!(split_combined("ˈʌvðˌə".to_string()), vec!["ˈʌv", "ðˌə"]);
+    assert_eq!(split_combined("kˈæt".to_string()), vec!["kˈæt"]);
+  }
+}
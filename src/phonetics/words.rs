@@ -0,0 +1,373 @@
+use espeakng::bindings;
+use napi_derive::napi;
+
+use crate::blocking;
+use crate::error::{Error, Result};
+use crate::lexicon::{self, LexiconMatch};
+use crate::normalize::{normalize_for_voice, NormalizedText};
+use crate::phonetics::notation::{ipa_to_kirshenbaum, PhonemeAlphabet};
+use crate::speaker::{self, Session};
+use crate::synth::{synthesize_with_events, SynthesisEvent, SynthesisEventKind};
+use crate::voice::DEFAULT_VOICE;
+use crate::PhonemizeOptions;
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct WordPhonemes {
+  /// The source text espeak read for the word
+  pub text: String,
+  /// Offset of the word in the source text, in chars
+  pub start: f64,
+  pub end: f64,
+  /// The phonemes espeak spoke for the word, without stress marks
+  pub phonemes: String,
+}
+
+/// Multi-letter phonemes espeak names as one, longest first, for splitting lexicon overrides
+const MULTI_LETTER_PHONEMES: [&str; 12] = [
+  "aɪə", "aʊə", "tʃ", "dʒ", "aɪ", "aʊ", "eɪ", "oʊ", "ɔɪ", "ɪə", "eə", "ʊə",
+];
+
+/** Joins the letters of a multi-letter phoneme name with the tie, leaving modifiers unjoined */
+fn tie_phoneme(name: &str, tie: Option<char>) -> String {
+  let tie = match tie {
+    Some(tie) => tie,
+    None => return name.to_string(),
+  };
+
+  let mut tied = String::new();
+  for (index, char) in name.chars().enumerate() {
+    let is_modifier =
+      matches!(char, 'ː' | 'ˑ' | 'ʰ' | 'ʲ' | 'ʷ' | 'ˠ' | 'ˤ') || !char.is_alphabetic();
+    if index > 0 && !is_modifier {
+      tied.push(tie);
+    }
+    tied.push(char);
+  }
+  tied
+}
+
+/**
+ * Groups synthesis events into IPA words. Each word event starts a word and the phoneme events after
+ * it make up its phonemes, so a source word espeak speaks as several words, such as a number,
+ * gives one entry per spoken word, all sharing its span
+ */
+pub fn group_word_events(
+  text: &str,
+  events: &[SynthesisEvent],
+  options: &PhonemizeOptions,
+) -> Vec<WordPhonemes> {
+  let chars = text.chars().collect::<Vec<_>>();
+  let tie = options.tie().char();
+
+  let mut words: Vec<WordPhonemes> = Vec::new();
+  for event in events {
+    match &event.kind {
+      SynthesisEventKind::Word => {
+        let start = event.text_position.min(chars.len());
+        let end = (event.text_position + event.length).min(chars.len());
+        words.push(WordPhonemes {
+          text: chars[start..end].iter().collect(),
+          start: start as f64,
+          end: end as f64,
+          phonemes: String::new(),
+        });
+      }
+      // Pauses are named with underscores and don't belong to any word
+      SynthesisEventKind::Phoneme(name) if !name.starts_with('_') => {
+        if let Some(word) = words.last_mut() {
+          word.phonemes.push_str(&tie_phoneme(name, tie));
+        }
+      }
+      _ => {}
+    }
+  }
+
+  words
+    .into_iter()
+    .filter(|word| !word.phonemes.is_empty())
+    .collect()
+}
+
+/** Joins the multi-letter phonemes of a lexicon override with the tie, as espeak would */
+pub(crate) fn tie_override(phonemes: &str, tie: Option<char>) -> String {
+  let mut rest = phonemes.to_string();
+  let mut tied = String::new();
+  while let Some(char) = rest.chars().next() {
+    let name = MULTI_LETTER_PHONEMES
+      .iter()
+      .find(|name| rest.starts_with(**name))
+      .map(|name| name.to_string())
+      .unwrap_or_else(|| char.to_string());
+    tied.push_str(&tie_phoneme(&name, tie));
+    rest.drain(..name.len());
+  }
+  tied
+}
+
+/**
+ * Writes a lexicon override's phonemes the way phoneme events are, without stress marks and with
+ * multi-letter phonemes joined by the tie
+ */
+fn override_phonemes(phonemes: &str, tie: Option<char>) -> String {
+  let unstressed = phonemes
+    .chars()
+    .filter(|char| !matches!(char, 'ˈ' | 'ˌ'))
+    .collect::<String>();
+  tie_override(&unstressed, tie)
+}
+
+/** Replaces the words espeak spoke inside each lexicon override with a single word */
+fn apply_overrides(
+  text: &str,
+  words: Vec<WordPhonemes>,
+  overrides: Vec<LexiconMatch>,
+  options: &PhonemizeOptions,
+) -> Vec<WordPhonemes> {
+  let mut overrides = overrides
+    .into_iter()
+    .map(|found| {
+      let start = text[..found.start].chars().count();
+      WordPhonemes {
+        text: text[found.start..found.end].to_string(),
+        start: start as f64,
+        end: (start + text[found.start..found.end].chars().count()) as f64,
+        phonemes: override_phonemes(&found.phonemes, options.tie().char()),
+      }
+    })
+    .peekable();
+
+  let mut result: Vec<WordPhonemes> = Vec::new();
+  let mut covering: Option<(f64, f64)> = None;
+  for word in words {
+    while let Some(found) = overrides.next_if(|found| found.start <= word.start) {
+      covering = Some((found.start, found.end));
+      result.push(found);
+    }
+    match covering {
+      Some((start, end)) if word.start >= start && word.end <= end => {}
+      _ => result.push(word),
+    }
+  }
+  result.extend(overrides);
+  result
+}
+
+/** Points words spoken from normalized text back at the original tokens they were expanded from */
+fn restore_offsets(
+  text: &str,
+  normalized: &NormalizedText,
+  words: Vec<WordPhonemes>,
+) -> Vec<WordPhonemes> {
+  let chars = text.chars().collect::<Vec<_>>();
+  words
+    .into_iter()
+    .map(|word| {
+      let (start, end) = normalized.original_range(word.start as usize, word.end as usize);
+      WordPhonemes {
+        text: chars[start..end].iter().collect(),
+        start: start as f64,
+        end: end as f64,
+        ..word
+      }
+    })
+    .collect()
+}
+
+/**
+ * Maps each word espeak speaks in the text to its source span, with the current voice. Lexicon
+ * overrides replace the words they cover
+ */
+pub fn word_phonemes(
+  session: &mut Session,
+  text: &str,
+  options: &PhonemizeOptions,
+) -> Result<Vec<WordPhonemes>> {
+  // Phoneme events are always named in IPA, so there's no way back to espeak's ASCII mnemonics
+  if options.alphabet() == PhonemeAlphabet::Ascii {
+    return Err(Error::InvalidOption(
+      "word phonemes are only available in IPA or Kirshenbaum".to_string(),
+    ));
+  }
+
+  let flags = if options.ssml.unwrap_or(false) {
+    bindings::espeakSSML
+  } else {
+    0
+  };
+  let normalized = if flags == 0 && options.normalize.unwrap_or(false) {
+    Some(normalize_for_voice(
+      text,
+      session.voice().unwrap_or(DEFAULT_VOICE),
+    )?)
+  } else {
+    None
+  };
+  let spoken = normalized
+    .as_ref()
+    .map(|normalized| normalized.text.as_str())
+    .unwrap_or(text);
+
+  let synthesis = synthesize_with_events(session, spoken, flags).map_err(|err| match err {
+    Error::Synthesis(err) => Error::Phonemization(err),
+    err => err,
+  })?;
+
+  let voice = session.voice().unwrap_or_default().to_string();
+  let overrides = lexicon::lexicon().find(spoken, &voice);
+  let mut words = apply_overrides(
+    spoken,
+    group_word_events(spoken, &synthesis.events, options),
+    overrides,
+    options,
+  );
+  if let Some(normalized) = &normalized {
+    words = restore_offsets(text, normalized, words);
+  }
+
+  Ok(match options.alphabet() {
+    PhonemeAlphabet::Kirshenbaum => words
+      .into_iter()
+      .map(|word| WordPhonemes {
+        phonemes: ipa_to_kirshenbaum(&word.phonemes),
+        ..word
+      })
+      .collect(),
+    PhonemeAlphabet::Ipa | PhonemeAlphabet::Ascii => words,
+  })
+}
+
+pub fn phonemize_words(
+  text: &str,
+  voice: &str,
+  options: &PhonemizeOptions,
+) -> Result<Vec<WordPhonemes>> {
+  let text = text.to_string();
+  let voice = voice.to_string();
+  let options = options.clone();
+  speaker::run(move |session| {
+    session.set_voice(&voice)?;
+    word_phonemes(session, &text, &options)
+  })
+}
+
+/**
+ * Phonemizes the text into a list of words, each with the span of source text espeak read for
+ * it, rather than leaving callers to split the phonemes on spaces
+ */
+#[napi(js_name = "phonemizeWords")]
+pub async fn phonemize_words_async(
+  text: String,
+  voice: Option<String>,
+  options: Option<PhonemizeOptions>,
+) -> napi::Result<Vec<WordPhonemes>> {
+  let voice = voice.unwrap_or_else(|| DEFAULT_VOICE.to_string());
+  let options = options.unwrap_or_default();
+  blocking::run(move || phonemize_words(text.as_str(), &voice, &options)).await
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{apply_overrides, group_word_events, override_phonemes, WordPhonemes};
+  use crate::lexicon::LexiconMatch;
+  use crate::phonetics::notation::PhonemeTie;
+  use crate::synth::{SynthesisEvent, SynthesisEventKind};
+  use crate::PhonemizeOptions;
+
+  fn event(kind: SynthesisEventKind, text_position: usize, length: usize) -> SynthesisEvent {
+    SynthesisEvent {
+      kind,
+      text_position,
+      length,
+      audio_position: 0.0,
+    }
+  }
+
+  fn phoneme(name: &str) -> SynthesisEvent {
+    event(SynthesisEventKind::Phoneme(name.to_string()), 0, 0)
+  }
+
+  #[test]
+  fn test_group_word_events() {
+    let events = vec![
+      event(SynthesisEventKind::Sentence, 0, 0),
+      phoneme("_"),
+      event(SynthesisEventKind::Word, 0, 4),
+      phoneme("tʃ"),
+      phoneme("aɪ"),
+      event(SynthesisEventKind::Word, 5, 2),
+      phoneme("f"),
+      phoneme("ɔː"),
+      phoneme("ɹ"),
+      phoneme("t"),
+      phoneme("i"),
+      event(SynthesisEventKind::Word, 5, 2),
+      phoneme("t"),
+      phoneme("uː"),
+      event(SynthesisEventKind::End, 7, 0),
+      phoneme("_:"),
+    ];
+    let options = PhonemizeOptions {
+      tie: Some(PhonemeTie::TieBar),
+      ..Default::default()
+    };
+
+    let words = group_word_events("chai 42", &events, &options)
+      .into_iter()
+      .map(|word| (word.text, word.start, word.end, word.phonemes))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      words,
+      vec![
+        ("chai".to_string(), 0.0, 4.0, "t͡ʃa͡ɪ".to_string()),
+        ("42".to_string(), 5.0, 7.0, "fɔːɹti".to_string()),
+        ("42".to_string(), 5.0, 7.0, "tuː".to_string()),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_apply_overrides() {
+    let word = |text: &str, start: f64, phonemes: &str| WordPhonemes {
+      text: text.to_string(),
+      start,
+      end: start + text.chars().count() as f64,
+      phonemes: phonemes.to_string(),
+    };
+    let words = vec![
+      word("my", 0.0, "maɪ"),
+      word("iPhone", 3.0, "aɪ"),
+      word("iPhone", 3.0, "foʊn"),
+      word("15", 10.0, "fɪftiːn"),
+      word("café", 13.0, "kæfeɪ"),
+    ];
+    let overrides = vec![LexiconMatch {
+      start: 3,
+      end: 12,
+      phonemes: "ˈaɪfoʊnfɪftˈiːn".to_string(),
+    }];
+
+    assert_eq!(
+      apply_overrides(
+        "my iPhone 15 café",
+        words,
+        overrides,
+        &PhonemizeOptions::default()
+      ),
+      vec![
+        word("my", 0.0, "maɪ"),
+        word("iPhone 15", 3.0, "aɪfoʊnfɪftiːn"),
+        word("café", 13.0, "kæfeɪ"),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_override_phonemes() {
+    assert_eq!(override_phonemes("ˈaɪfoʊn", None), "aɪfoʊn");
+    assert_eq!(
+      override_phonemes("tʃˈaɪ lˌɑːteɪ", Some('\u{361}')),
+      "t͡ʃa͡ɪ lɑːte͡ɪ"
+    );
+  }
+}
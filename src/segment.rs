@@ -0,0 +1,286 @@
+use napi_derive::napi;
+
+/// Abbreviations whose full stop never ends a sentence, such as titles before a name
+pub const ABBREVIATIONS: [&str; 19] = [
+  "mr", "mrs", "ms", "mx", "dr", "prof", "rev", "hon", "gen", "capt", "sgt", "lt", "col", "mt",
+  "e.g", "i.e", "cf", "vs", "viz",
+];
+/// Abbreviations which only skip a sentence end before a number, as in "No. 5", since they're
+/// also ordinary words such as "no" or "CA"
+pub const NUMBER_ABBREVIATIONS: [&str; 9] = [
+  "no", "nos", "vol", "pp", "fig", "figs", "ca", "st", "approx",
+];
+const TERMINATORS: [char; 4] = ['.', '?', '!', '…'];
+/// Punctuation closing a sentence after its terminator, as in `"Stop."` or `(Really?)`
+const CLOSERS: [char; 7] = ['"', '\'', ')', ']', '”', '’', '»'];
+/// Punctuation opening a word, stripped before looking it up as an abbreviation
+const OPENERS: [char; 7] = ['"', '\'', '(', '[', '“', '‘', '«'];
+
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct SegmentOptions {
+  /// Abbreviations to recognise on top of the built in English ones, with or without their full stop
+  pub abbreviations: Option<Vec<String>>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sentence {
+  pub text: String,
+  /// Offset of the sentence in the source text, in chars
+  pub start: f64,
+  pub end: f64,
+}
+
+/** A sentence's byte range in the source text, along with its char offsets */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SentenceSpan {
+  pub byte_start: usize,
+  pub byte_end: usize,
+  pub start: usize,
+  pub end: usize,
+}
+
+struct Segmenter<'a> {
+  chars: Vec<(usize, char)>,
+  text: &'a str,
+  abbreviations: Vec<String>,
+}
+
+impl Segmenter<'_> {
+  fn span(&self, start: usize, end: usize) -> SentenceSpan {
+    SentenceSpan {
+      byte_start: self.chars[start].0,
+      byte_end: self
+        .chars
+        .get(end)
+        .map(|(byte, _)| *byte)
+        .unwrap_or(self.text.len()),
+      start,
+      end,
+    }
+  }
+
+  /** The char index after the last non-whitespace char before `end` */
+  fn trim_end(&self, start: usize, end: usize) -> usize {
+    (start..end)
+      .rev()
+      .find(|index| !self.chars[*index].1.is_whitespace())
+      .map(|index| index + 1)
+      .unwrap_or(start)
+  }
+
+  /** Whether a line break at the index is followed by another, with only whitespace between */
+  fn is_paragraph_break(&self, index: usize) -> bool {
+    self.chars[index + 1..]
+      .iter()
+      .take_while(|(_, char)| char.is_whitespace())
+      .any(|(_, char)| *char == '\n')
+  }
+
+  /** The word before the char at the index, without opening punctuation */
+  fn word_before(&self, index: usize) -> String {
+    let start = (0..index)
+      .rev()
+      .find(|index| self.chars[*index].1.is_whitespace())
+      .map(|index| index + 1)
+      .unwrap_or(0);
+    self.chars[start..index]
+      .iter()
+      .map(|(_, char)| *char)
+      .skip_while(|char| OPENERS.contains(char))
+      .collect()
+  }
+
+  /**
+   * Whether the terminators from `terminator` to `end` end the sentence. Text continuing in
+   * lower case never starts a new sentence, and a lone full stop after an abbreviation or an
+   * initial doesn't end one
+   */
+  fn is_boundary(&self, terminator: usize, end: usize) -> bool {
+    let next = self.chars[end..]
+      .iter()
+      .map(|(_, char)| *char)
+      .find(|char| !char.is_whitespace() && !OPENERS.contains(char));
+    if next.map(char::is_lowercase).unwrap_or(false) {
+      return false;
+    }
+
+    let is_full_stop = self.chars[terminator].1 == '.'
+      && !self
+        .chars
+        .get(terminator + 1)
+        .map(|(_, char)| TERMINATORS.contains(char))
+        .unwrap_or(false);
+    if !is_full_stop {
+      return true;
+    }
+
+    let word = self.word_before(terminator);
+    let mut letters = word.chars();
+    let is_initial =
+      matches!((letters.next(), letters.next()), (Some(char), None) if char.is_uppercase());
+    let word = word.to_lowercase();
+    let is_abbreviation = ABBREVIATIONS.contains(&word.as_str())
+      || self.abbreviations.contains(&word)
+      || (NUMBER_ABBREVIATIONS.contains(&word.as_str()) && next.is_some_and(char::is_numeric));
+    !is_initial && !is_abbreviation
+  }
+
+  fn segment(&self) -> Vec<SentenceSpan> {
+    let mut spans = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut index = 0;
+
+    while index < self.chars.len() {
+      let char = self.chars[index].1;
+      let sentence_start = match start {
+        Some(sentence_start) => sentence_start,
+        None => {
+          if !char.is_whitespace() {
+            start = Some(index);
+          }
+          index += 1;
+          continue;
+        }
+      };
+
+      if char == '\n' && self.is_paragraph_break(index) {
+        spans.push(self.span(sentence_start, self.trim_end(sentence_start, index)));
+        start = None;
+      } else if TERMINATORS.contains(&char) {
+        let mut end = index;
+        while end < self.chars.len() && TERMINATORS.contains(&self.chars[end].1) {
+          end += 1;
+        }
+        while end < self.chars.len() && CLOSERS.contains(&self.chars[end].1) {
+          end += 1;
+        }
+
+        // Terminators inside a word, as in "3.14" or "e.g.", can't end a sentence
+        let is_word_end = self
+          .chars
+          .get(end)
+          .map(|(_, char)| char.is_whitespace())
+          .unwrap_or(true);
+        if is_word_end && self.is_boundary(index, end) {
+          spans.push(self.span(sentence_start, end));
+          start = None;
+        }
+        index = end;
+        continue;
+      }
+      index += 1;
+    }
+
+    if let Some(sentence_start) = start {
+      let end = self.trim_end(sentence_start, self.chars.len());
+      spans.push(self.span(sentence_start, end));
+    }
+    spans
+  }
+}
+
+/**
+ * Splits the text into sentences at terminal punctuation and paragraph breaks, without splitting
+ * abbreviations such as "Dr. Smith" or "e.g.", initials or decimals. Whitespace between
+ * sentences isn't part of either
+ */
+pub fn segment_sentences(text: &str, options: &SegmentOptions) -> Vec<SentenceSpan> {
+  Segmenter {
+    chars: text.char_indices().collect(),
+    text,
+    abbreviations: options
+      .abbreviations
+      .iter()
+      .flatten()
+      .map(|abbreviation| abbreviation.trim().trim_end_matches('.').to_lowercase())
+      .collect(),
+  }
+  .segment()
+}
+
+/** Splits the text into sentences with their char offsets, the same way phonemization does */
+#[napi(js_name = "segmentSentences")]
+pub fn segment_sentences_js(text: String, options: Option<SegmentOptions>) -> Vec<Sentence> {
+  segment_sentences(&text, &options.unwrap_or_default())
+    .into_iter()
+    .map(|span| Sentence {
+      text: text[span.byte_start..span.byte_end].to_string(),
+      start: span.start as f64,
+      end: span.end as f64,
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{segment_sentences, SegmentOptions};
+
+  fn sentences<'a>(text: &'a str, options: &SegmentOptions) -> Vec<&'a str> {
+    segment_sentences(text, options)
+      .into_iter()
+      .map(|span| &text[span.byte_start..span.byte_end])
+      .collect()
+  }
+
+  #[test]
+  fn test_segment_sentences() {
+    let options = SegmentOptions::default();
+    assert_eq!(
+      sentences(
+        "  Dr. Smith paid $3.14, e.g. for J. K. Rowling's book. Was it \"good?\" Yes!\n\nNew paragraph",
+        &options
+      ),
+      vec![
+        "Dr. Smith paid $3.14, e.g. for J. K. Rowling's book.",
+        "Was it \"good?\"",
+        "Yes!",
+        "New paragraph"
+      ]
+    );
+    assert_eq!(
+      sentences("It was 2020. Then it wasn't… and so on.", &options),
+      vec!["It was 2020.", "Then it wasn't… and so on."]
+    );
+
+    // Number abbreviations only continue the sentence before a number
+    assert_eq!(
+      sentences("See No. 5 and approx. 3 more, fig. 2. Done.", &options),
+      vec!["See No. 5 and approx. 3 more, fig. 2.", "Done."]
+    );
+    assert_eq!(
+      sentences(
+        "The answer is no. Then we left. I moved to CA. Then it rained.",
+        &options
+      ),
+      vec![
+        "The answer is no.",
+        "Then we left.",
+        "I moved to CA.",
+        "Then it rained."
+      ]
+    );
+
+    let options = SegmentOptions {
+      abbreviations: Some(vec!["Approx.".to_string(), "Corp".to_string()]),
+    };
+    assert_eq!(
+      sentences("Acme Corp. Ltd. Hello", &options),
+      vec!["Acme Corp. Ltd.", "Hello"]
+    );
+  }
+
+  #[test]
+  fn test_segment_sentences_offsets() {
+    let text = "Ça va? Oui.";
+    let spans = segment_sentences(text, &SegmentOptions::default());
+    assert_eq!(
+      spans
+        .iter()
+        .map(|span| (span.start, span.end))
+        .collect::<Vec<_>>(),
+      vec![(0, 6), (7, 11)]
+    );
+  }
+}
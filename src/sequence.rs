@@ -0,0 +1,243 @@
+use std::ops::Range;
+
+use crate::leven::leven_phoneme_relative;
+use crate::{Chunk, PhonemeChunk};
+
+/// Most words or phoneme words a single step can match at once
+const MAX_SPAN: usize = 4;
+/// Cost of leaving a word or a phoneme word unmatched
+const SKIP_COST: f32 = 1.0;
+/// Cost of each extra word or phoneme word in a step, so one to one matches win ties
+const MERGE_COST: f32 = 0.25;
+/// Cost of a complex token matching as many phoneme words as espeak speaks it as
+const EXPECTED_SPAN_COST: f32 = 0.1;
+/// Smallest distance from the diagonal searched, in phoneme words
+const MIN_BAND: usize = 32;
+
+/** Words matched to phoneme words. Either range is empty when the other side was skipped */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+  pub words: Range<usize>,
+  pub phonemes: Range<usize>,
+}
+
+#[derive(Clone, Copy)]
+struct Cell {
+  cost: f32,
+  /// Words and phoneme words consumed by the step ending here
+  words: u8,
+  phonemes: u8,
+}
+
+/** Cost matrix limited to a band around the diagonal, so long texts stay near linear */
+struct Band {
+  rows: Vec<Vec<Cell>>,
+  starts: Vec<usize>,
+}
+
+impl Band {
+  fn get(&self, word: usize, phoneme: usize) -> Option<&Cell> {
+    let start = self.starts[word];
+    phoneme
+      .checked_sub(start)
+      .and_then(|offset| self.rows[word].get(offset))
+  }
+}
+
+fn step_cost(
+  words: &[Chunk],
+  phonemes: &[PhonemeChunk],
+  spans: &[Option<usize>],
+  step: &Step,
+) -> f32 {
+  let matched_phonemes = phonemes[step.phonemes.clone()]
+    .iter()
+    .map(|phoneme| phoneme.value.as_str())
+    .collect::<String>();
+  // Distances are relative to length, so merged steps are weighted by how much they cover to
+  // keep them from being cheaper than matching their parts separately
+  let merged_count = (step.words.len() + step.phonemes.len() - 1) as f32;
+  let extra = (merged_count - 1.0) * MERGE_COST;
+
+  if step.words.len() == 1 {
+    let word = &words[step.words.start];
+    if spans[step.words.start] == Some(step.phonemes.len()) {
+      return EXPECTED_SPAN_COST;
+    }
+    return leven_phoneme_relative(word, &matched_phonemes) * merged_count + extra;
+  }
+
+  let merged = Chunk {
+    value: words[step.words.clone()]
+      .iter()
+      .map(|word| word.value.as_str())
+      .collect(),
+    ..Default::default()
+  };
+  leven_phoneme_relative(&merged, &matched_phonemes) * merged_count + extra
+}
+
+/**
+ * Finds the cheapest monotonic alignment of words to phoneme words, in the manner of
+ * Needleman-Wunsch. A word can match several phoneme words and several words can match one,
+ * scored with `leven_phoneme_relative`, and either side can be skipped. `spans` gives how many
+ * phoneme words a complex token is expected to be spoken as, if known
+ */
+pub fn align_sequences(
+  words: &[Chunk],
+  phonemes: &[PhonemeChunk],
+  spans: &[Option<usize>],
+) -> Vec<Step> {
+  let (word_count, phoneme_count) = (words.len(), phonemes.len());
+  let width = MIN_BAND + word_count.abs_diff(phoneme_count);
+
+  let mut band = Band {
+    rows: Vec::with_capacity(word_count + 1),
+    starts: Vec::with_capacity(word_count + 1),
+  };
+  for word in 0..=word_count {
+    let center = (word * phoneme_count).checked_div(word_count).unwrap_or(0);
+    let start = center.saturating_sub(width);
+    let end = (center + width).min(phoneme_count);
+
+    let mut row = Vec::with_capacity(end - start + 1);
+    for phoneme in start..=end {
+      let mut best = Cell {
+        cost: if word == 0 && phoneme == 0 {
+          0.0
+        } else {
+          f32::INFINITY
+        },
+        words: 0,
+        phonemes: 0,
+      };
+
+      let mut consider = |word_span: usize, phoneme_span: usize, cost: f32| {
+        let previous = if word_span == 0 {
+          phoneme
+            .checked_sub(start + 1)
+            .and_then(|offset| row.get(offset).copied())
+        } else {
+          band.get(word - word_span, phoneme - phoneme_span).copied()
+        };
+        if let Some(previous) = previous {
+          if previous.cost + cost < best.cost {
+            best = Cell {
+              cost: previous.cost + cost,
+              words: word_span as u8,
+              phonemes: phoneme_span as u8,
+            };
+          }
+        }
+      };
+
+      if word > 0 {
+        consider(1, 0, SKIP_COST);
+      }
+      if phoneme > 0 {
+        consider(0, 1, SKIP_COST);
+      }
+      for phoneme_span in 1..=MAX_SPAN.min(phoneme) {
+        if word > 0 {
+          let step = Step {
+            words: word - 1..word,
+            phonemes: phoneme - phoneme_span..phoneme,
+          };
+          consider(1, phoneme_span, step_cost(words, phonemes, spans, &step));
+        }
+      }
+      if phoneme > 0 {
+        for word_span in 2..=MAX_SPAN.min(word) {
+          let step = Step {
+            words: word - word_span..word,
+            phonemes: phoneme - 1..phoneme,
+          };
+          consider(word_span, 1, step_cost(words, phonemes, spans, &step));
+        }
+      }
+
+      row.push(best);
+    }
+    band.rows.push(row);
+    band.starts.push(start);
+  }
+
+  let mut steps = Vec::new();
+  let (mut word, mut phoneme) = (word_count, phoneme_count);
+  while word > 0 || phoneme > 0 {
+    let cell = band.get(word, phoneme).copied().unwrap();
+    let (previous_word, previous_phoneme) =
+      (word - cell.words as usize, phoneme - cell.phonemes as usize);
+    steps.push(Step {
+      words: previous_word..word,
+      phonemes: previous_phoneme..phoneme,
+    });
+    word = previous_word;
+    phoneme = previous_phoneme;
+  }
+  steps.reverse();
+  steps
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{align_sequences, Step};
+  use crate::{Chunk, PhonemeChunk};
+
+  fn word(value: &str) -> Chunk {
+    Chunk {
+      value: value.to_string(),
+      ..Default::default()
+    }
+  }
+
+  fn phoneme(value: &str) -> PhonemeChunk {
+    PhonemeChunk {
+      value: value.to_string(),
+      value_word: String::new(),
+      start: 0.0,
+      end: 0.0,
+      start_time: 0.0,
+      end_time: 0.0,
+    }
+  }
+
+  #[test]
+  fn test_align_sequences() {
+    // "extra" isn't spoken and "2021" is spoken as three words
+    let words = ["the", "extra", "year", "2021", "was", "long"].map(word);
+    let phonemes = ["ðə", "jɪɹ", "twɛnti", "twɛnti", "wʌn", "wʌz", "lɔŋ"].map(phoneme);
+    let spans = [None, None, None, Some(3), None, None];
+
+    let steps = align_sequences(&words, &phonemes, &spans);
+    assert_eq!(
+      steps,
+      vec![
+        Step {
+          words: 0..1,
+          phonemes: 0..1
+        },
+        Step {
+          words: 1..2,
+          phonemes: 1..1
+        },
+        Step {
+          words: 2..3,
+          phonemes: 1..2
+        },
+        Step {
+          words: 3..4,
+          phonemes: 2..5
+        },
+        Step {
+          words: 4..5,
+          phonemes: 5..6
+        },
+        Step {
+          words: 5..6,
+          phonemes: 6..7
+        },
+      ]
+    );
+  }
+}
@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::ffi::{c_void, CStr, CString};
+use std::os::raw::c_int;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread;
+
+use espeakng::bindings;
+use once_cell::sync::Lazy;
+
+use crate::error::{Error, Result};
+use crate::normalize::normalize_for_voice;
+use crate::phonetics::notation::{ipa_to_kirshenbaum, phoneme_mode, trace_mode, PhonemeAlphabet};
+use crate::phonetics::words::tie_override;
+use crate::segment::{segment_sentences, SegmentOptions};
+use crate::{cache, lexicon, synth, voice, PhonemizeOptions};
+
+/// Maximum number of jobs waiting on the speaker before callers block
+pub const QUEUE_CAPACITY: usize = 64;
+
+type Job = Box<dyn FnOnce(&mut SpeakerState) + Send>;
+
+/**
+ * espeak-ng keeps its state in globals, so every call goes through a single worker thread which
+ * initialises espeak once and then serialises jobs from a bounded queue
+ */
+static QUEUE: Lazy<SyncSender<Job>> = Lazy::new(|| {
+  let (sender, receiver) = mpsc::sync_channel::<Job>(QUEUE_CAPACITY);
+  thread::Builder::new()
+    .name("espeak-speaker".to_string())
+    .spawn(move || run_worker(receiver))
+    .expect("failed to spawn the espeak speaker thread");
+  sender
+});
+
+fn run_worker(receiver: Receiver<Job>) {
+  let mut state = SpeakerState::default();
+  for job in receiver {
+    // A panicking job drops its reply channel, which surfaces to the caller as an error, so the
+    // worker can carry on with the next job
+    let _ = catch_unwind(AssertUnwindSafe(|| job(&mut state)));
+  }
+}
+
+#[derive(Default)]
+struct SpeakerState {
+  /// Whether espeak has been set up for synthesis events
+  initialised: bool,
+  /// Identifier of the voice espeak currently has loaded
+  current_voice: Option<String>,
+  /// Requested voice or language -> installed voice identifier
+  resolved_voices: HashMap<String, String>,
+  /// espeak-ng-data directory espeak loads from, or its default when unset
+  data_path: Option<PathBuf>,
+  /// Directory we created for `data_path`, removed once it's no longer used
+  temporary_data_path: Option<PathBuf>,
+}
+
+impl SpeakerState {
+  fn run<T>(&mut self, job: impl FnOnce(&mut Session) -> Result<T>) -> Result<T> {
+    let mut speaker = espeakng::initialise(None)
+      .map_err(Error::EspeakInit)?
+      .lock();
+    if !self.initialised {
+      synth::initialise_events(self.data_path.as_deref())?;
+      // Reinitialising resets espeak to its default voice
+      self.current_voice = None;
+      self.initialised = true;
+    }
+    job(&mut Session {
+      speaker: &mut speaker,
+      state: self,
+    })
+  }
+}
+
+/** Exclusive access to the initialised espeak speaker for the duration of a job */
+pub struct Session<'a> {
+  pub speaker: &'a mut espeakng::Speaker,
+  state: &'a mut SpeakerState,
+}
+
+impl Session<'_> {
+  /** Switches to the given voice or language, skipping the switch if it's already loaded */
+  pub fn set_voice(&mut self, voice: &str) -> Result<()> {
+    let identifier = match self.state.resolved_voices.get(voice) {
+      Some(identifier) => identifier.clone(),
+      None => {
+        let identifier = voice::resolve_voice(voice)?;
+        self
+          .state
+          .resolved_voices
+          .insert(voice.to_string(), identifier.clone());
+        identifier
+      }
+    };
+
+    if self.state.current_voice.as_deref() != Some(identifier.as_str()) {
+      self
+        .speaker
+        .set_voice_raw(&identifier)
+        .map_err(|_| Error::VoiceNotFound(voice.to_string()))?;
+      self.state.current_voice = Some(identifier);
+    }
+    Ok(())
+  }
+
+  /** The espeak-ng-data directory espeak loads from, if not its default */
+  pub fn data_path(&self) -> Option<&Path> {
+    self.state.data_path.as_deref()
+  }
+
+  /**
+   * Reinitialises espeak with another espeak-ng-data directory, or its default. Temporary
+   * directories are deleted once espeak no longer loads from them
+   */
+  pub fn set_data_path(&mut self, path: Option<PathBuf>, temporary: bool) -> Result<()> {
+    synth::initialise_events(path.as_deref())?;
+    // Dictionaries in the new data may pronounce words differently
+    cache::invalidate();
+    self.state.current_voice = None;
+    // Another data directory may have different voices installed
+    self.state.resolved_voices.clear();
+
+    if let Some(previous) = self.state.temporary_data_path.take() {
+      if path.as_ref() != Some(&previous) {
+        let _ = std::fs::remove_dir_all(previous);
+      }
+    }
+    if temporary {
+      self.state.temporary_data_path = path.clone();
+    }
+    self.state.data_path = path;
+    Ok(())
+  }
+
+  /** Stops tracking the temporary data directory, leaving the caller to delete it */
+  pub fn take_temporary_data_path(&mut self) -> Option<PathBuf> {
+    self.state.temporary_data_path.take()
+  }
+
+  /** Identifier of the voice espeak currently has loaded */
+  pub fn voice(&self) -> Option<&str> {
+    self.state.current_voice.as_deref()
+  }
+
+  /**
+   * Phonemizes the text with the current voice. Words in the lexicon are replaced with their
+   * override rather than being passed to espeak, except in SSML or ASCII output
+   */
+  pub fn text_to_phonemes(&mut self, text: &str, options: &PhonemizeOptions) -> Result<String> {
+    let phonemes = if options.ssml.unwrap_or(false) {
+      synth::trace_phonemes(self, text, bindings::espeakSSML, trace_mode(options))?
+    } else {
+      let normalized;
+      let text = if options.normalize.unwrap_or(false) {
+        normalized = normalize_for_voice(text, self.voice().unwrap_or(voice::DEFAULT_VOICE))?;
+        normalized.text.as_str()
+      } else {
+        text
+      };
+
+      let overrides = match options.alphabet() {
+        // Overrides are written in IPA, which can't be converted to espeak's ASCII mnemonics
+        PhonemeAlphabet::Ascii => Vec::new(),
+        PhonemeAlphabet::Ipa | PhonemeAlphabet::Kirshenbaum => {
+          lexicon::lexicon().find(text, self.voice().unwrap_or_default())
+        }
+      };
+
+      let mut pieces = Vec::new();
+      let mut offset = 0;
+      for found in overrides {
+        pieces.push(self.espeak_phonemes(&text[offset..found.start], options)?);
+        pieces.push(tie_override(&found.phonemes, options.tie().char()));
+        offset = found.end;
+      }
+      pieces.push(self.espeak_phonemes(&text[offset..], options)?);
+
+      pieces
+        .into_iter()
+        .filter(|piece| !piece.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+    };
+
+    Ok(match options.alphabet() {
+      PhonemeAlphabet::Kirshenbaum => ipa_to_kirshenbaum(&phonemes),
+      PhonemeAlphabet::Ipa | PhonemeAlphabet::Ascii => phonemes,
+    })
+  }
+
+  /**
+   * Phonemizes the text with espeak a sentence at a time. espeak only returns one clause per
+   * call, so each sentence is fed through until espeak reports it has reached the end
+   */
+  fn espeak_phonemes(&mut self, text: &str, options: &PhonemizeOptions) -> Result<String> {
+    let phoneme_mode = phoneme_mode(options) as c_int;
+    let mut clauses = Vec::new();
+
+    for span in segment_sentences(text, &SegmentOptions::default()) {
+      let sentence = CString::new(text[span.byte_start..span.byte_end].replace('\0', ""))
+        .expect("NUL bytes were removed");
+      let mut pointer = sentence.as_ptr() as *const c_void;
+      while !pointer.is_null() {
+        let previous = pointer;
+        let phonemes = unsafe {
+          bindings::espeak_TextToPhonemes(
+            &mut pointer,
+            bindings::espeakCHARS_UTF8 as c_int,
+            phoneme_mode,
+          )
+        };
+        if phonemes.is_null() {
+          break;
+        }
+        clauses.push(
+          unsafe { CStr::from_ptr(phonemes) }
+            .to_string_lossy()
+            .to_string(),
+        );
+        // Guards against looping forever should espeak stop advancing through the text
+        if pointer == previous {
+          break;
+        }
+      }
+    }
+
+    Ok(
+      clauses
+        .into_iter()
+        .filter(|clause| !clause.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join(" "),
+    )
+  }
+}
+
+/**
+ * Queues the job on the speaker thread and blocks until it has run. Blocks while the queue is
+ * full. Jobs must not call back into `run` since the speaker thread would wait on itself
+ */
+pub fn run<T, F>(job: F) -> Result<T>
+where
+  T: Send + 'static,
+  F: FnOnce(&mut Session) -> Result<T> + Send + 'static,
+{
+  let (reply_sender, reply_receiver) = mpsc::sync_channel(1);
+  QUEUE
+    .send(Box::new(move |state: &mut SpeakerState| {
+      let _ = reply_sender.send(state.run(job));
+    }))
+    .map_err(|_| Error::SpeakerUnavailable)?;
+
+  reply_receiver
+    .recv()
+    .map_err(|_| Error::SpeakerUnavailable)?
+}
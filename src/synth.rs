@@ -0,0 +1,423 @@
+use std::ffi::CString;
+use std::os::raw::{c_int, c_short, c_void};
+use std::panic::catch_unwind;
+use std::path::Path;
+
+use espeakng::{bindings, Parameter};
+use napi::bindgen_prelude::Buffer;
+use napi_derive::napi;
+
+use crate::blocking;
+use crate::error::{Error, Result};
+use crate::speaker::{self, Session};
+use crate::voice::DEFAULT_VOICE;
+
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct SynthesisOptions {
+  pub voice: Option<String>,
+  /// Words per minute, between 80 and 450
+  pub rate: Option<u32>,
+  /// Base pitch, between 0 and 100
+  pub pitch: Option<u32>,
+  /// Volume, between 0 and 200. Values above 100 may distort
+  pub volume: Option<u32>,
+  /// Wraps the samples in a WAV header rather than returning raw PCM
+  pub wav: Option<bool>,
+}
+
+#[napi(object)]
+pub struct SynthesisResult {
+  /// 16-bit little endian mono PCM, or a WAV file if requested
+  pub audio: Buffer,
+  pub sample_rate: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SynthesisEventKind {
+  /// Start of a sentence
+  Sentence,
+  /// Start of a word
+  Word,
+  /// Start of a phoneme, named in IPA
+  Phoneme(String),
+  /// End of a sentence or clause
+  End,
+  /// End of the synthesized text
+  MessageEnd,
+}
+
+#[derive(Clone, Debug)]
+pub struct SynthesisEvent {
+  pub kind: SynthesisEventKind,
+  /// Offset of the source text in chars, starting at 0
+  pub text_position: usize,
+  /// Length of the source text in chars, only set for words
+  pub length: usize,
+  /// Time in milliseconds from the start of the audio
+  pub audio_position: f64,
+}
+
+/** Audio and events collected while espeak synthesizes a piece of text */
+#[derive(Default)]
+pub struct Synthesis {
+  pub samples: Vec<i16>,
+  pub events: Vec<SynthesisEvent>,
+  pub sample_rate: u32,
+}
+
+impl Synthesis {
+  /** Length of the synthesized audio in milliseconds */
+  pub fn duration(&self) -> f64 {
+    if self.sample_rate == 0 {
+      return 0.0;
+    }
+    self.samples.len() as f64 / self.sample_rate as f64 * 1000.0
+  }
+
+  /**
+   * The time at which the event at the index stops, which is the start of the next word,
+   * sentence or clause end after it, or the end of the audio
+   */
+  pub fn event_end_time(&self, index: usize) -> f64 {
+    self.events[index + 1..]
+      .iter()
+      .find(|event| {
+        matches!(
+          event.kind,
+          SynthesisEventKind::Word
+            | SynthesisEventKind::Sentence
+            | SynthesisEventKind::End
+            | SynthesisEventKind::MessageEnd
+        )
+      })
+      .map(|event| event.audio_position)
+      .unwrap_or_else(|| self.duration())
+      .max(self.events[index].audio_position)
+  }
+
+  /** The samples as 16-bit little endian PCM */
+  pub fn to_pcm(&self) -> Vec<u8> {
+    self
+      .samples
+      .iter()
+      .flat_map(|sample| sample.to_le_bytes())
+      .collect()
+  }
+
+  /** The samples as a 16-bit mono WAV file */
+  pub fn to_wav(&self) -> Vec<u8> {
+    let data = self.to_pcm();
+    let byte_rate = self.sample_rate * 2;
+
+    let mut wav = Vec::with_capacity(44 + data.len());
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, mono
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&self.sample_rate.to_le_bytes());
+    wav.extend_from_slice(&byte_rate.to_le_bytes());
+    // Block align and bits per sample
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    wav.extend_from_slice(&data);
+    wav
+  }
+}
+
+fn to_event(event: &bindings::espeak_EVENT) -> Option<SynthesisEvent> {
+  let kind = match event.type_ {
+    bindings::espeak_EVENT_TYPE_espeakEVENT_SENTENCE => SynthesisEventKind::Sentence,
+    bindings::espeak_EVENT_TYPE_espeakEVENT_WORD => SynthesisEventKind::Word,
+    bindings::espeak_EVENT_TYPE_espeakEVENT_END => SynthesisEventKind::End,
+    bindings::espeak_EVENT_TYPE_espeakEVENT_MSG_TERMINATED => SynthesisEventKind::MessageEnd,
+    bindings::espeak_EVENT_TYPE_espeakEVENT_PHONEME => {
+      // Phoneme names are stored inline, zero terminated unless they use all 8 bytes
+      let name = unsafe { event.id.string }
+        .iter()
+        .take_while(|byte| **byte != 0)
+        .map(|byte| *byte as u8)
+        .collect::<Vec<_>>();
+      SynthesisEventKind::Phoneme(String::from_utf8_lossy(&name).to_string())
+    }
+    _ => return None,
+  };
+
+  Some(SynthesisEvent {
+    kind,
+    // espeak counts positions from 1
+    text_position: (event.text_position.max(1) - 1) as usize,
+    length: event.length.max(0) as usize,
+    audio_position: event.audio_position.max(0) as f64,
+  })
+}
+
+unsafe extern "C" fn synth_callback(
+  wav: *mut c_short,
+  sample_count: c_int,
+  events: *mut bindings::espeak_EVENT,
+) -> c_int {
+  let result = catch_unwind(|| {
+    if events.is_null() {
+      return 0;
+    }
+
+    // Every event in the list carries the user data of the message being synthesized
+    let synthesis = unsafe { (*events).user_data } as *mut Synthesis;
+    if synthesis.is_null() {
+      return 0;
+    }
+    let synthesis = unsafe { &mut *synthesis };
+
+    if !wav.is_null() && sample_count > 0 {
+      synthesis
+        .samples
+        .extend_from_slice(unsafe { std::slice::from_raw_parts(wav, sample_count as usize) });
+    }
+
+    let mut event = events;
+    loop {
+      let current = unsafe { *event };
+      if current.type_ == bindings::espeak_EVENT_TYPE_espeakEVENT_LIST_TERMINATED {
+        break;
+      }
+      synthesis.events.extend(to_event(&current));
+      event = unsafe { event.add(1) };
+    }
+    0
+  });
+
+  // Returning 1 asks espeak to stop synthesizing rather than unwinding into C
+  result.unwrap_or(1)
+}
+
+pub(crate) fn status_to_error(status: u32) -> espeakng::Error {
+  match espeakng::ESpeakNgError::from_repr(status) {
+    Some(err) => espeakng::Error::ESpeakNg(err),
+    None => espeakng::Error::OtherC(None),
+  }
+}
+
+/**
+ * Reinitialises espeak with phoneme events enabled and installs our synthesis callback, which
+ * espeakng's own initialisation doesn't expose. Loads data from the given espeak-ng-data
+ * directory, or the default one. Must be called from the speaker thread, after which
+ * `Speaker::synthesize` must not be used since the callback expects a `Synthesis`
+ */
+pub(crate) fn initialise_events(data_path: Option<&Path>) -> Result<()> {
+  let options = bindings::espeakINITIALIZE_PHONEME_EVENTS
+    | bindings::espeakINITIALIZE_PHONEME_IPA
+    | bindings::espeakINITIALIZE_DONT_EXIT;
+
+  let data_path = data_path
+    .map(|path| CString::new(path.to_string_lossy().as_bytes()))
+    .transpose()
+    .map_err(|_| Error::InvalidOption("the data path must not contain NUL".to_string()))?;
+
+  let sample_rate = unsafe {
+    bindings::espeak_Initialize(
+      bindings::espeak_AUDIO_OUTPUT_AUDIO_OUTPUT_SYNCHRONOUS,
+      0,
+      data_path
+        .as_ref()
+        .map(|path| path.as_ptr())
+        .unwrap_or(std::ptr::null()),
+      options as c_int,
+    )
+  };
+  if sample_rate <= 0 {
+    return Err(Error::EspeakInit(espeakng::Error::OtherC(None)));
+  }
+
+  unsafe { bindings::espeak_SetSynthCallback(Some(synth_callback)) };
+  Ok(())
+}
+
+/** Synthesizes the text with the current voice, collecting the audio along with its events */
+pub fn synthesize_with_events(_session: &mut Session, text: &str, flags: u32) -> Result<Synthesis> {
+  let text = CString::new(text.replace('\0', "")).unwrap();
+  let size = text.as_bytes_with_nul().len();
+
+  let mut synthesis = Synthesis {
+    sample_rate: unsafe { bindings::espeak_ng_GetSampleRate() }.max(0) as u32,
+    ..Default::default()
+  };
+
+  let status = unsafe {
+    bindings::espeak_ng_Synthesize(
+      text.as_ptr() as *const c_void,
+      size as _,
+      0,
+      bindings::espeak_POSITION_TYPE_POS_CHARACTER,
+      0,
+      bindings::espeakCHARS_UTF8 | flags,
+      std::ptr::null_mut(),
+      &mut synthesis as *mut Synthesis as *mut c_void,
+    )
+  };
+  if status != 0 {
+    return Err(Error::Synthesis(status_to_error(status)));
+  }
+
+  // Synchronous output mode only returns once the callback has seen every event, but wait for
+  // espeak to settle regardless since `synthesis` must outlive the callback
+  let status = unsafe { bindings::espeak_ng_Synchronize() };
+  if status != 0 {
+    return Err(Error::Synthesis(status_to_error(status)));
+  }
+
+  Ok(synthesis)
+}
+
+pub(crate) unsafe fn read_file(file: *mut bindings::FILE) -> String {
+  unsafe {
+    bindings::fseek(file, 0, bindings::SEEK_END as c_int);
+    let length = bindings::ftell(file).max(0) as usize;
+    bindings::rewind(file);
+
+    let mut buffer = vec![0u8; length];
+    let read = bindings::fread(buffer.as_mut_ptr() as *mut c_void, 1, length as _, file);
+    buffer.truncate(read as usize);
+    String::from_utf8_lossy(&buffer).to_string()
+  }
+}
+
+/**
+ * Synthesizes the text while espeak traces the phonemes it speaks to a temporary file, in the
+ * notation given by the `espeak_SetPhonemeTrace` mode. Unlike `espeak_TextToPhonemes`, this
+ * honours synthesis flags such as `espeakSSML`
+ */
+pub fn trace_phonemes(
+  session: &mut Session,
+  text: &str,
+  flags: u32,
+  trace_mode: u32,
+) -> Result<String> {
+  let file = unsafe { bindings::tmpfile() };
+  if file.is_null() {
+    return Err(Error::Phonemization(espeakng::Error::OtherC(None)));
+  }
+
+  unsafe { bindings::espeak_SetPhonemeTrace(trace_mode as c_int, file) };
+  let synthesis = synthesize_with_events(session, text, flags);
+  // Reset the trace so later synthesis doesn't write to the closed file
+  unsafe { bindings::espeak_SetPhonemeTrace(0, std::ptr::null_mut()) };
+
+  let phonemes = unsafe { read_file(file) };
+  unsafe { bindings::fclose(file) };
+
+  synthesis.map_err(|err| match err {
+    Error::Synthesis(err) => Error::Phonemization(err),
+    err => err,
+  })?;
+  Ok(phonemes)
+}
+
+fn validate_range(name: &str, value: Option<u32>, min: u32, max: u32) -> Result<()> {
+  match value {
+    Some(value) if value < min || value > max => Err(Error::InvalidOption(format!(
+      "{} must be between {} and {} but got {}",
+      name, min, max, value
+    ))),
+    _ => Ok(()),
+  }
+}
+
+/** Synthesizes the text, restoring espeak's default rate, pitch and volume afterwards */
+pub fn synthesize(text: &str, options: &SynthesisOptions) -> Result<Synthesis> {
+  validate_range("rate", options.rate, 80, 450)?;
+  validate_range("pitch", options.pitch, 0, 100)?;
+  validate_range("volume", options.volume, 0, 200)?;
+
+  let text = text.to_string();
+  let options = options.clone();
+  speaker::run(move |session| {
+    session.set_voice(options.voice.as_deref().unwrap_or(DEFAULT_VOICE))?;
+
+    let parameters = [
+      (Parameter::Rate, options.rate),
+      (Parameter::Pitch, options.pitch),
+      (Parameter::Volume, options.volume),
+    ];
+    let synthesis = parameters
+      .iter()
+      .filter_map(|(parameter, value)| value.map(|value| (*parameter, value)))
+      .try_for_each(|(parameter, value)| {
+        session
+          .speaker
+          .set_parameter(parameter, value as i32, false)
+          .map_err(Error::Synthesis)
+      })
+      .and_then(|_| synthesize_with_events(session, &text, 0));
+
+    // Every parameter is restored even if setting one or synthesis failed, as the speaker is
+    // shared with later jobs
+    let mut restored = Ok(());
+    for (parameter, value) in parameters {
+      if value.is_some() {
+        let default = session.speaker.get_parameter(parameter, true);
+        let result = session
+          .speaker
+          .set_parameter(parameter, default, false)
+          .map_err(Error::Synthesis);
+        restored = restored.and(result);
+      }
+    }
+
+    let synthesis = synthesis?;
+    restored?;
+    Ok(synthesis)
+  })
+}
+
+#[napi(js_name = "synthesize")]
+pub async fn synthesize_audio(
+  text: String,
+  options: Option<SynthesisOptions>,
+) -> napi::Result<SynthesisResult> {
+  let options = options.unwrap_or_default();
+  let wav = options.wav.unwrap_or(false);
+  let synthesis = blocking::run(move || synthesize(text.as_str(), &options)).await?;
+
+  Ok(SynthesisResult {
+    audio: if wav {
+      synthesis.to_wav()
+    } else {
+      synthesis.to_pcm()
+    }
+    .into(),
+    sample_rate: synthesis.sample_rate,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Synthesis;
+
+  #[test]
+  fn test_to_wav() {
+    let synthesis = Synthesis {
+      samples: vec![0, 1, -1],
+      events: Vec::new(),
+      sample_rate: 22050,
+    };
+    let wav = synthesis.to_wav();
+
+    assert_eq!(wav.len(), 44 + 6);
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(u32::from_le_bytes([wav[4], wav[5], wav[6], wav[7]]), 36 + 6);
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    assert_eq!(
+      u32::from_le_bytes([wav[24], wav[25], wav[26], wav[27]]),
+      22050
+    );
+    assert_eq!(&wav[36..40], b"data");
+    assert_eq!(&wav[44..], &[0, 0, 1, 0, 255, 255]);
+  }
+}
@@ -0,0 +1,259 @@
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::align::is_word;
+use crate::boundary::BoundaryProfile;
+use crate::Chunk;
+
+/// Endings split from a contraction as their own part, such as the "'ve" of "couldn't've"
+const CLITICS: [&str; 6] = ["ve", "ll", "re", "s", "d", "m"];
+
+#[napi]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub enum WordSegmentation {
+  /// Unicode word boundaries (UAX #29), which never split a grapheme cluster
+  #[default]
+  Unicode,
+  /// The original chunker, which splits on a fixed set of punctuation and whitespace
+  Legacy,
+}
+
+/**
+ * Splits the text into word chunks with char offsets, using the given segmentation. Unicode
+ * segmentation separates words by whitespace and the profile's punctuation other than joiners,
+ * while the legacy chunker keeps its own fixed list
+ */
+pub fn split_words(
+  text: &str,
+  segmentation: WordSegmentation,
+  boundaries: &BoundaryProfile,
+) -> Vec<Chunk> {
+  match segmentation {
+    WordSegmentation::Unicode => split_text_to_word_chunks_unicode(text, boundaries),
+    WordSegmentation::Legacy => split_text_to_word_chunks(text),
+  }
+}
+
+/**
+ * Splits the text on Unicode word boundaries. Adjacent segments not separated by whitespace or
+ * punctuation form one word, so "well-known" and "3.50" stay whole, and segments without any
+ * letters, numbers or currency are trimmed from either end, so "(hello)" becomes "hello".
+ * Segments are whole grapheme clusters, so combining marks and emoji sequences are never split
+ */
+pub fn split_text_to_word_chunks_unicode(text: &str, boundaries: &BoundaryProfile) -> Vec<Chunk> {
+  let mut chunks = Vec::new();
+  // Segments of the current word, each with its char offset
+  let mut segments: Vec<(usize, &str)> = Vec::new();
+  let mut offset = 0;
+
+  let mut push_word = |segments: &mut Vec<(usize, &str)>| {
+    let first = segments.iter().position(|(_, segment)| is_word(segment));
+    let last = segments.iter().rposition(|(_, segment)| is_word(segment));
+    if let (Some(first), Some(last)) = (first, last) {
+      let (start, _) = segments[first];
+      let (last_start, last_segment) = segments[last];
+      chunks.push(Chunk {
+        value: segments[first..=last]
+          .iter()
+          .map(|(_, segment)| *segment)
+          .collect(),
+        start: start as f64,
+        end: (last_start + last_segment.chars().count()) as f64,
+        ..Default::default()
+      });
+    }
+    segments.clear();
+  };
+
+  for segment in text.split_word_bounds() {
+    if segment.chars().all(|char| boundaries.is_separator(char)) {
+      push_word(&mut segments);
+    } else {
+      segments.push((offset, segment));
+    }
+    offset += segment.chars().count();
+  }
+  push_word(&mut segments);
+  chunks
+}
+
+fn is_hyphen(char: char) -> bool {
+  matches!(char, '-' | '\u{2010}' | '\u{2011}' | '\u{5be}')
+}
+
+fn is_apostrophe(char: char) -> bool {
+  matches!(char, '\'' | '’')
+}
+
+/**
+ * Splits a hyphenated compound or contraction into its parts, each with its own char offsets.
+ * Hyphens are dropped from between the parts, while contractions are only split before a clitic
+ * such as "'ve" or "'ll", which keeps its apostrophe. Only the profile's joiners split a word,
+ * and a word which doesn't split returns no parts
+ */
+pub fn split_compound(word: &Chunk, boundaries: &BoundaryProfile) -> Vec<Chunk> {
+  let chars = word.value.chars().collect::<Vec<_>>();
+  let mut parts = Vec::new();
+  let mut start = 0;
+  let mut push_part = |start: usize, end: usize| {
+    if start < end {
+      parts.push(Chunk {
+        value: chars[start..end].iter().collect(),
+        start: word.start + start as f64,
+        end: word.start + end as f64,
+        ..Default::default()
+      });
+    }
+  };
+
+  for (index, &char) in chars.iter().enumerate() {
+    let previous = index.checked_sub(1).map(|previous| chars[previous]);
+    if !boundaries.joins(previous, char, chars.get(index + 1).copied()) {
+      continue;
+    }
+    if is_hyphen(char) {
+      push_part(start, index);
+      start = index + 1;
+    } else if is_apostrophe(char) {
+      let clitic = chars[index + 1..]
+        .iter()
+        .take_while(|char| char.is_alphanumeric())
+        .collect::<String>()
+        .to_lowercase();
+      if CLITICS.contains(&clitic.as_str()) {
+        push_part(start, index);
+        start = index;
+      }
+    }
+  }
+  if start == 0 {
+    return Vec::new();
+  }
+  push_part(start, chars.len());
+  parts
+}
+
+pub fn split_text_to_word_chunks(text: &str) -> Vec<Chunk> {
+  // TODO: Replace with reduce or map with accumulator
+  let mut character_counter = 0;
+  let ignored_chars = [',', '.', '?', '!', ':', '[', ']', '{', '}', '"', ' ', '\n'];
+
+  text
+    .split_inclusive(ignored_chars)
+    .filter_map(|word| -> Option<Chunk> {
+      // We use .chars().count() to get the number of characters rather than
+      // the number of bytes
+      let full_word_len = word.chars().count();
+
+      // Remove the punctuation the end
+      let trimmed_word = word
+        .chars()
+        .filter(|char| !ignored_chars.contains(char))
+        .collect::<String>();
+      let trimmed_word_len = trimmed_word.chars().count();
+
+      let start = character_counter;
+      let end = character_counter + trimmed_word_len;
+
+      // Track the number of chars we've reached
+      if word != "CS" {
+        character_counter += full_word_len
+      };
+
+      // If it's not a word, continue
+      if !is_word(trimmed_word.as_str()) {
+        return None;
+      }
+
+      // Otherwise, add the word to our list
+      Some(Chunk {
+        value: trimmed_word,
+        start: start as f64,
+        start_time: 0.0,
+        end: end as f64,
+        end_time: 0.0,
+        ..Default::default()
+      })
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{split_compound, split_text_to_word_chunks, split_text_to_word_chunks_unicode};
+  use crate::boundary::BoundaryProfile;
+  use crate::Chunk;
+
+  #[test]
+  fn test_split_text_to_word_chunks_unicode() {
+    let text = "Cafe\u{301} 👍🏽 is (well-known), U.S.A. 3.50!";
+    let words = split_text_to_word_chunks_unicode(text, &BoundaryProfile::default())
+      .into_iter()
+      .map(|chunk| (chunk.value, chunk.start, chunk.end))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      words,
+      vec![
+        ("Cafe\u{301}".to_string(), 0.0, 5.0),
+        ("is".to_string(), 9.0, 11.0),
+        ("well-known".to_string(), 13.0, 23.0),
+        ("U.S.A".to_string(), 26.0, 31.0),
+        ("3.50".to_string(), 33.0, 37.0),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_split_text_to_word_chunks() {
+    // Matches the original chunker, which only splits on its fixed list of punctuation
+    let text = "Hello (world); a well-known—test, ok.";
+    let words = split_text_to_word_chunks(text)
+      .into_iter()
+      .map(|chunk| (chunk.value, chunk.start, chunk.end))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      words,
+      vec![
+        ("Hello".to_string(), 0.0, 5.0),
+        ("(world);".to_string(), 6.0, 14.0),
+        ("a".to_string(), 15.0, 16.0),
+        ("well-known—test".to_string(), 17.0, 32.0),
+        ("ok".to_string(), 34.0, 36.0),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_split_compound() {
+    let parts = |value: &str| {
+      let word = Chunk {
+        value: value.to_string(),
+        start: 10.0,
+        end: 10.0 + value.chars().count() as f64,
+        ..Default::default()
+      };
+      split_compound(&word, &BoundaryProfile::default())
+        .into_iter()
+        .map(|part| (part.value, part.start, part.end))
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(
+      parts("state-of-the-art"),
+      vec![
+        ("state".to_string(), 10.0, 15.0),
+        ("of".to_string(), 16.0, 18.0),
+        ("the".to_string(), 19.0, 22.0),
+        ("art".to_string(), 23.0, 26.0),
+      ]
+    );
+    assert_eq!(
+      parts("couldn't've"),
+      vec![
+        ("couldn't".to_string(), 10.0, 18.0),
+        ("'ve".to_string(), 18.0, 21.0),
+      ]
+    );
+    assert!(parts("don't").is_empty());
+  }
+}
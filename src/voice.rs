@@ -0,0 +1,69 @@
+use napi_derive::napi;
+
+use crate::blocking;
+use crate::error::{Error, Result};
+use crate::speaker;
+
+pub const DEFAULT_VOICE: &str = "en-us";
+
+#[napi(object)]
+#[derive(Clone)]
+pub struct VoiceInfo {
+  pub name: String,
+  pub identifier: String,
+  pub languages: Vec<String>,
+}
+
+impl From<espeakng::Voice> for VoiceInfo {
+  fn from(voice: espeakng::Voice) -> Self {
+    VoiceInfo {
+      name: voice.name,
+      identifier: voice.filename,
+      languages: voice
+        .languages
+        .into_iter()
+        .map(|language| language.name)
+        .collect(),
+    }
+  }
+}
+
+/** Lists the voices installed with espeak-ng. Must be called from the speaker thread */
+fn installed_voices() -> Vec<VoiceInfo> {
+  espeakng::Speaker::get_voices()
+    .into_iter()
+    .map(VoiceInfo::from)
+    .collect()
+}
+
+/**
+ * Resolves a voice or language such as "es", "en-US", "gmw/en-US" or "German" to the
+ * identifier of an installed espeak-ng voice. Must be called from the speaker thread
+ */
+pub fn resolve_voice(voice: &str) -> Result<String> {
+  let voice = voice.trim();
+  let is_match = |candidate: &str| candidate.eq_ignore_ascii_case(voice);
+
+  let voices = installed_voices();
+  voices
+    .iter()
+    // Prefer exact identifiers and names before falling back to languages, since a language
+    // such as "en" is spoken by multiple voices
+    .find(|info| {
+      is_match(&info.identifier)
+        || is_match(info.identifier.rsplit('/').next().unwrap_or_default())
+        || is_match(&info.name)
+    })
+    .or_else(|| {
+      voices
+        .iter()
+        .find(|info| info.languages.iter().any(|language| is_match(language)))
+    })
+    .map(|info| info.identifier.clone())
+    .ok_or_else(|| Error::VoiceNotFound(voice.to_string()))
+}
+
+#[napi]
+pub async fn list_voices() -> napi::Result<Vec<VoiceInfo>> {
+  blocking::run(|| speaker::run(|_| Ok(installed_voices()))).await
+}